@@ -0,0 +1,1282 @@
+use crate::dates::{self, day_number};
+use crate::money::{self, Money};
+use crate::structs::{Household, Split, SplitShare, Transaction, TransactionKind};
+use crate::traits::{BusinessLogic, BusinessLogicError, Settlement, TransactionChange};
+use rational::Rational;
+use std::collections::{BTreeMap, HashMap};
+use toml::value::Date;
+use uuid::Uuid;
+
+/// How many of the largest expenses the statistics list.
+const LARGEST_EXPENSES: usize = 5;
+
+/// In-memory implementation of BusinessLogic.
+///
+/// Works on transactions that were already loaded from persistence, one slice
+/// per ledger. All amounts are computed in integer minor units and every
+/// transaction is split with `money::allocate`, so its shares always sum
+/// exactly to its amount and every device shows the same numbers.
+///
+/// With households set, balances are netted per household: members appear
+/// under the household id and debts between members of one household vanish.
+/// Shares and consumption stay individual.
+pub struct BalanceEngine<'a> {
+    ledgers: HashMap<Uuid, &'a [Transaction]>,
+    /// Map from member id -> household id
+    households: HashMap<Uuid, Uuid>,
+}
+
+impl<'a> BalanceEngine<'a> {
+    pub fn new() -> Self {
+        Self {
+            ledgers: HashMap::new(),
+            households: HashMap::new(),
+        }
+    }
+
+    /// Make the transactions of a ledger available to the calculations.
+    pub fn ledger(mut self, ledger_id: Uuid, transactions: &'a [Transaction]) -> Self {
+        self.ledgers.insert(ledger_id, transactions);
+        self
+    }
+
+    /// Net balances and settlements per household instead of per entity.
+    pub fn households(mut self, households: &[Household]) -> Self {
+        for household in households {
+            for member in &household.members {
+                self.households.insert(*member, household.id);
+            }
+        }
+        self
+    }
+
+    /// The party an entity settles as: its household, or itself.
+    fn party(&self, entity_id: Uuid) -> Uuid {
+        self.households
+            .get(&entity_id)
+            .copied()
+            .unwrap_or(entity_id)
+    }
+
+    /// What each entity consumed in a ledger: the sum of its shares.
+    ///
+    /// Always per entity, also when balances are netted per household.
+    pub fn calculate_consumption(
+        &self,
+        ledger_id: Uuid,
+    ) -> Result<HashMap<Uuid, Money>, BusinessLogicError> {
+        let transactions = self.transactions(ledger_id)?;
+        let currency = common_currency(transactions)?;
+        let mut consumption: HashMap<Uuid, i64> = HashMap::new();
+
+        for transaction in transactions {
+            // Transfers move money, nobody consumes anything
+            if let TransactionKind::Transfer { .. } = transaction.kind {
+                continue;
+            }
+            for (entity_id, share) in self.transaction_shares(transaction) {
+                *consumption.entry(entity_id).or_insert(0) += share.minor_units();
+            }
+        }
+
+        Ok(to_money(consumption, currency))
+    }
+
+    fn transactions(&self, ledger_id: Uuid) -> Result<&'a [Transaction], BusinessLogicError> {
+        self.ledgers
+            .get(&ledger_id)
+            .copied()
+            .ok_or(BusinessLogicError::LedgerNotFound(ledger_id))
+    }
+
+    fn find_transaction(&self, transaction_id: Uuid) -> Option<&'a Transaction> {
+        self.ledgers
+            .values()
+            .flat_map(|transactions| transactions.iter())
+            .find(|transaction| transaction.id == transaction_id)
+    }
+
+    /// Net balances summed over every ledger given to the engine, per currency.
+    ///
+    /// Each ledger has to be in a single currency, but ledgers may differ.
+    pub fn calculate_group_net_balances(
+        &self,
+    ) -> Result<BTreeMap<String, HashMap<Uuid, Money>>, BusinessLogicError> {
+        let mut totals: BTreeMap<String, HashMap<Uuid, i64>> = BTreeMap::new();
+        for ledger_id in self.ledger_ids() {
+            for (entity_id, balance) in self.calculate_net_balances(ledger_id)? {
+                *totals
+                    .entry(balance.currency().to_string())
+                    .or_default()
+                    .entry(entity_id)
+                    .or_insert(0) += balance.minor_units();
+            }
+        }
+
+        Ok(totals
+            .into_iter()
+            .map(|(currency, balances)| {
+                let balances = to_money(balances, Some(currency.clone()));
+                (currency, balances)
+            })
+            .collect())
+    }
+
+    /// Transfers that settle every ledger in `currency` on its own, as
+    /// (ledger id, settlement).
+    ///
+    /// Used to record a group-wide settlement: people pay what
+    /// `calculate_settlements` computes for the group net balances, and each
+    /// ledger gets these balancing transfers. Per entity, the transfers add up
+    /// to exactly the same amounts as the group-wide payments.
+    pub fn ledger_settlements(
+        &self,
+        currency: &str,
+    ) -> Result<Vec<(Uuid, Settlement)>, BusinessLogicError> {
+        let mut settlements = Vec::new();
+        for ledger_id in self.ledger_ids() {
+            let balances: HashMap<Uuid, Money> = self
+                .calculate_net_balances(ledger_id)?
+                .into_iter()
+                .filter(|(_, balance)| balance.currency() == currency)
+                .collect();
+            for settlement in self.calculate_settlements(balances) {
+                settlements.push((ledger_id, settlement));
+            }
+        }
+        Ok(settlements)
+    }
+
+    /// Ids of all ledgers given to the engine, sorted for stable results.
+    fn ledger_ids(&self) -> Vec<Uuid> {
+        let mut ledger_ids: Vec<Uuid> = self.ledgers.keys().copied().collect();
+        ledger_ids.sort();
+        ledger_ids
+    }
+
+    /// Split a transaction's amount between the entities of its split ratios.
+    ///
+    /// Itemized transactions are split per line item instead. Tax, tip and
+    /// any part of the amount that is not itemized are then allocated in
+    /// proportion to what everyone had on the bill. The recipient of a
+    /// transfer carries its full amount. A refund of a known transaction is
+    /// shared in proportion to that transaction's shares.
+    ///
+    /// The result is sorted by entity id and sums exactly to the amount.
+    pub fn transaction_shares(&self, transaction: &Transaction) -> Vec<(Uuid, Money)> {
+        match transaction.kind {
+            TransactionKind::Transfer { to_entity } => {
+                vec![(to_entity, transaction.amount.clone())]
+            }
+            TransactionKind::Refund {
+                original_transaction_id: Some(original_id),
+            } => match self.find_transaction(original_id) {
+                Some(original) if original.id != transaction.id => {
+                    let weights: Vec<(Uuid, Rational)> = split_shares(original)
+                        .into_iter()
+                        .map(|(entity_id, share)| {
+                            (
+                                entity_id,
+                                Rational::new(share.minor_units().abs() as i128, 1),
+                            )
+                        })
+                        .collect();
+                    money::allocate(&transaction.amount, &weights)
+                }
+                _ => split_shares(transaction),
+            },
+            _ => split_shares(transaction),
+        }
+    }
+
+    /// What each entity paid minus its share of a transaction.
+    ///
+    /// Positive values = the transaction leaves the entity owed money.
+    pub fn transaction_nets(&self, transaction: &Transaction) -> BTreeMap<Uuid, i64> {
+        let mut nets = BTreeMap::new();
+        for (entity_id, paid) in transaction.payments() {
+            *nets.entry(entity_id).or_insert(0) += paid.minor_units();
+        }
+        for (entity_id, share) in self.transaction_shares(transaction) {
+            *nets.entry(entity_id).or_insert(0) -= share.minor_units();
+        }
+        nets
+    }
+
+    /// Who owes whom because of a transaction, as (debtor, creditor, amount).
+    ///
+    /// With a single payer, everyone owes the payer their share. With several
+    /// payers, each debt is split between everyone who paid more than their
+    /// share, in proportion to how much they overpaid.
+    pub fn transaction_debts(&self, transaction: &Transaction) -> Vec<(Uuid, Uuid, Money)> {
+        let nets = self.transaction_nets(transaction);
+        let creditors: Vec<(Uuid, Rational)> = nets
+            .iter()
+            .filter(|(_, net)| **net > 0)
+            .map(|(entity_id, net)| (*entity_id, Rational::new(*net as i128, 1)))
+            .collect();
+
+        let mut debts = Vec::new();
+        for (debtor, net) in nets.iter().filter(|(_, net)| **net < 0) {
+            let debt = Money::new(-net, transaction.amount.currency());
+            for (creditor, amount) in money::allocate(&debt, &creditors) {
+                if !amount.is_zero() {
+                    debts.push((*debtor, creditor, amount));
+                }
+            }
+        }
+        debts
+    }
+}
+
+/// Granularity of the spending over time in `LedgerStatistics`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Period {
+    Day,
+    Month,
+}
+
+impl Period {
+    /// Label of the period a date falls into, e.g. `"2025-12-27"` or `"2025-12"`.
+    pub fn label(&self, date: Date) -> String {
+        match self {
+            Period::Day => date.to_string(),
+            Period::Month => format!("{:04}-{:02}", date.year, date.month),
+        }
+    }
+}
+
+/// What an entity paid and consumed, see `LedgerStatistics`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityStatistics {
+    pub paid: Money,
+    pub consumed: Money,
+}
+
+/// Spending statistics of a ledger over a date range.
+///
+/// Spending is everything but transfers, with refunds and income counted
+/// negative. All amounts are in the ledger's currency.
+pub struct LedgerStatistics<'a> {
+    pub total: Money,
+    pub per_entity: BTreeMap<Uuid, EntityStatistics>,
+    /// Keyed by category key, `None` for uncategorized transactions
+    pub per_category: BTreeMap<Option<String>, Money>,
+    /// Keyed by `Period::label`, so iteration is chronological
+    pub per_period: BTreeMap<String, Money>,
+    /// The largest expenses, largest first
+    pub largest_expenses: Vec<&'a Transaction>,
+}
+
+impl BalanceEngine<'_> {
+    /// Spending statistics of a ledger between `from` and `to` (inclusive,
+    /// either may be open), by transaction date. `None` for a ledger without
+    /// transactions or with several currencies, which has no single currency
+    /// to count in.
+    pub fn calculate_statistics(
+        &self,
+        ledger_id: Uuid,
+        from: Option<Date>,
+        to: Option<Date>,
+        period: Period,
+    ) -> Result<Option<LedgerStatistics<'_>>, BusinessLogicError> {
+        let transactions = self.transactions(ledger_id)?;
+        let currency = match common_currency(transactions) {
+            Ok(Some(currency)) => currency,
+            Ok(None) | Err(BusinessLogicError::CurrencyMismatch { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let in_range =
+            |transaction: &Transaction| match transaction.transaction_datetime_rfc_3339.date {
+                Some(date) => {
+                    from.is_none_or(|from| day_number(date) >= day_number(from))
+                        && to.is_none_or(|to| day_number(date) <= day_number(to))
+                }
+                None => from.is_none() && to.is_none(),
+            };
+        let spending: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|transaction| !matches!(transaction.kind, TransactionKind::Transfer { .. }))
+            .filter(|transaction| in_range(transaction))
+            .collect();
+
+        let mut total = 0;
+        let mut paid: BTreeMap<Uuid, i64> = BTreeMap::new();
+        let mut consumed: BTreeMap<Uuid, i64> = BTreeMap::new();
+        let mut per_category: BTreeMap<Option<String>, i64> = BTreeMap::new();
+        let mut per_period: BTreeMap<String, i64> = BTreeMap::new();
+        for transaction in &spending {
+            let amount = transaction.amount.minor_units();
+            total += amount;
+            for (entity_id, payment) in transaction.payments() {
+                *paid.entry(entity_id).or_insert(0) += payment.minor_units();
+            }
+            for (entity_id, share) in self.transaction_shares(transaction) {
+                *consumed.entry(entity_id).or_insert(0) += share.minor_units();
+            }
+            *per_category
+                .entry(transaction.category.clone())
+                .or_insert(0) += amount;
+            if let Some(date) = transaction.transaction_datetime_rfc_3339.date {
+                *per_period.entry(period.label(date)).or_insert(0) += amount;
+            }
+        }
+
+        let mut largest_expenses: Vec<&Transaction> = spending
+            .into_iter()
+            .filter(|transaction| transaction.kind == TransactionKind::Expense)
+            .collect();
+        largest_expenses.sort_by_key(|transaction| {
+            (
+                std::cmp::Reverse(transaction.amount.minor_units()),
+                transaction.id,
+            )
+        });
+        largest_expenses.truncate(LARGEST_EXPENSES);
+
+        let money = |units: i64| Money::new(units, currency.clone());
+        let entity_ids: Vec<Uuid> = paid.keys().chain(consumed.keys()).copied().collect();
+        Ok(Some(LedgerStatistics {
+            total: money(total),
+            per_entity: entity_ids
+                .into_iter()
+                .map(|entity_id| {
+                    let statistics = EntityStatistics {
+                        paid: money(paid.get(&entity_id).copied().unwrap_or(0)),
+                        consumed: money(consumed.get(&entity_id).copied().unwrap_or(0)),
+                    };
+                    (entity_id, statistics)
+                })
+                .collect(),
+            per_category: per_category
+                .into_iter()
+                .map(|(category, units)| (category, money(units)))
+                .collect(),
+            per_period: per_period
+                .into_iter()
+                .map(|(label, units)| (label, money(units)))
+                .collect(),
+            largest_expenses,
+        }))
+    }
+}
+
+/// Net balances of every party right after a transaction, see
+/// `BalanceEngine::balance_history`.
+pub struct BalancePoint<'a> {
+    pub transaction: &'a Transaction,
+    pub balances: BTreeMap<Uuid, Money>,
+}
+
+impl<'a> BalanceEngine<'a> {
+    /// Replay a ledger's transactions in chronological order and record the
+    /// net balance of every party after each one.
+    ///
+    /// Transactions with the same time are ordered by id, so every device
+    /// gets the same series. Once a party appears it stays in the balances,
+    /// and the last point equals `calculate_net_balances`.
+    pub fn balance_history(
+        &self,
+        ledger_id: Uuid,
+    ) -> Result<Vec<BalancePoint<'a>>, BusinessLogicError> {
+        let transactions = self.transactions(ledger_id)?;
+        let currency = common_currency(transactions)?;
+        let mut ordered: Vec<&'a Transaction> = transactions.iter().collect();
+        ordered.sort_by_key(|transaction| {
+            (
+                dates::timestamp(&transaction.transaction_datetime_rfc_3339),
+                transaction.id,
+            )
+        });
+
+        let mut balances: BTreeMap<Uuid, i64> = BTreeMap::new();
+        let mut history = Vec::with_capacity(ordered.len());
+        for transaction in ordered {
+            for (entity_id, net) in self.transaction_nets(transaction) {
+                *balances.entry(self.party(entity_id)).or_insert(0) += net;
+            }
+            let currency = currency.clone().unwrap_or_default();
+            history.push(BalancePoint {
+                transaction,
+                balances: balances
+                    .iter()
+                    .map(|(party, units)| (*party, Money::new(*units, currency.clone())))
+                    .collect(),
+            });
+        }
+        Ok(history)
+    }
+}
+
+impl BalanceEngine<'_> {
+    /// How much a set of changes moved an entity's net balance: its net of
+    /// every new version minus its net of every old version.
+    ///
+    /// `None` if there are no changes.
+    pub fn balance_impact(
+        &self,
+        changes: &[TransactionChange],
+        entity_id: Uuid,
+    ) -> Result<Option<Money>, BusinessLogicError> {
+        let versions: Vec<(i64, &Transaction)> = changes
+            .iter()
+            .flat_map(|change| {
+                let before = change.before.iter().map(|transaction| (-1, transaction));
+                let after = change.after.iter().map(|transaction| (1, transaction));
+                before.chain(after)
+            })
+            .collect();
+        let currency = match common_currency(versions.iter().map(|(_, transaction)| *transaction))?
+        {
+            Some(currency) => currency,
+            None => return Ok(None),
+        };
+
+        let impact: i64 = versions
+            .iter()
+            .map(|(sign, transaction)| {
+                sign * self
+                    .transaction_nets(transaction)
+                    .get(&entity_id)
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .sum();
+        Ok(Some(Money::new(impact, currency)))
+    }
+}
+
+/// Split a transaction by its split ratios or line items, ignoring its kind.
+fn split_shares(transaction: &Transaction) -> Vec<(Uuid, Money)> {
+    if transaction.line_items.is_empty() {
+        return split_amount(&transaction.amount, &transaction.split_ratios);
+    }
+
+    let mut totals: BTreeMap<Uuid, i64> = BTreeMap::new();
+    let mut itemized = 0;
+    for item in &transaction.line_items {
+        for (entity_id, share) in split_amount(&item.amount, &item.splits) {
+            *totals.entry(entity_id).or_insert(0) += share.minor_units();
+        }
+        itemized += item.amount.minor_units();
+    }
+
+    let currency = transaction.amount.currency();
+    let extras = [&transaction.tax, &transaction.tip]
+        .into_iter()
+        .flatten()
+        .map(Money::minor_units)
+        .sum::<i64>();
+    let weights: Vec<(Uuid, Rational)> = totals
+        .iter()
+        .map(|(entity_id, units)| (*entity_id, Rational::new(*units as i128, 1)))
+        .collect();
+
+    // The validator reports bills whose items, tax and tip do not add up to
+    // the amount. They still count with the full amount, shared like the items.
+    if itemized + extras != transaction.amount.minor_units() {
+        return money::allocate(&transaction.amount, &weights);
+    }
+
+    for (entity_id, share) in money::allocate(&Money::new(extras, currency), &weights) {
+        *totals.entry(entity_id).or_insert(0) += share.minor_units();
+    }
+
+    totals
+        .into_iter()
+        .map(|(entity_id, units)| (entity_id, Money::new(units, currency)))
+        .collect()
+}
+
+/// Split an amount between the entities of the given splits.
+fn split_amount(amount: &Money, splits: &[Split]) -> Vec<(Uuid, Money)> {
+    let ratios: Vec<(Uuid, Rational)> = splits
+        .iter()
+        .map(|split| split.entity_id)
+        .zip(split_weights(amount, splits))
+        .collect();
+    money::allocate(amount, &ratios)
+}
+
+/// Unnormalized weight of every split, whatever its mode.
+///
+/// Exact amounts weigh their minor units, so they are reproduced exactly when
+/// they add up to the amount. For equal-plus-adjustment, the amount left after
+/// all adjustments is split equally and each adjustment is added on top.
+fn split_weights(amount: &Money, splits: &[Split]) -> Vec<Rational> {
+    let adjustments: Vec<i128> = splits
+        .iter()
+        .filter_map(|split| match &split.share {
+            SplitShare::Adjustment(adjustment) => Some(adjustment.minor_units() as i128),
+            _ => None,
+        })
+        .collect();
+    let adjusted_count = adjustments.len().max(1) as i128;
+    let remainder = amount.minor_units() as i128 - adjustments.iter().sum::<i128>();
+
+    splits
+        .iter()
+        .map(|split| match &split.share {
+            SplitShare::Ratio(ratio) | SplitShare::Percentage(ratio) => *ratio,
+            SplitShare::Exact(exact) => Rational::new(exact.minor_units() as i128, 1),
+            SplitShare::Shares(shares) => Rational::new(*shares as i128, 1),
+            SplitShare::Adjustment(adjustment) => Rational::new(
+                remainder + adjusted_count * adjustment.minor_units() as i128,
+                adjusted_count,
+            ),
+        })
+        .collect()
+}
+
+impl Default for BalanceEngine<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The single currency all transactions are in, `None` if there are none.
+fn common_currency<'t>(
+    transactions: impl IntoIterator<Item = &'t Transaction>,
+) -> Result<Option<String>, BusinessLogicError> {
+    let mut currency: Option<&str> = None;
+    for transaction in transactions {
+        let found = transaction.amount.currency();
+        match currency {
+            Some(expected) if expected != found => {
+                return Err(BusinessLogicError::CurrencyMismatch {
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                });
+            }
+            _ => currency = Some(found),
+        }
+    }
+    Ok(currency.map(str::to_string))
+}
+
+fn to_money(balances: HashMap<Uuid, i64>, currency: Option<String>) -> HashMap<Uuid, Money> {
+    match currency {
+        Some(currency) => balances
+            .into_iter()
+            .map(|(entity_id, units)| (entity_id, Money::new(units, currency.clone())))
+            .collect(),
+        None => HashMap::new(),
+    }
+}
+
+impl BusinessLogic for BalanceEngine<'_> {
+    fn calculate_balances(
+        &self,
+        ledger_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<HashMap<Uuid, Money>, BusinessLogicError> {
+        let transactions = self.transactions(ledger_id)?;
+        let currency = common_currency(transactions)?;
+        let user_party = self.party(user_id);
+        let mut balances: HashMap<Uuid, i64> = HashMap::new();
+
+        for transaction in transactions {
+            for (debtor, creditor, amount) in self.transaction_debts(transaction) {
+                let (debtor, creditor) = (self.party(debtor), self.party(creditor));
+                if debtor == creditor {
+                    continue;
+                }
+                if creditor == user_party {
+                    *balances.entry(debtor).or_insert(0) += amount.minor_units();
+                } else if debtor == user_party {
+                    *balances.entry(creditor).or_insert(0) -= amount.minor_units();
+                }
+            }
+        }
+
+        Ok(to_money(balances, currency))
+    }
+
+    fn calculate_net_balances(
+        &self,
+        ledger_id: Uuid,
+    ) -> Result<HashMap<Uuid, Money>, BusinessLogicError> {
+        let transactions = self.transactions(ledger_id)?;
+        let currency = common_currency(transactions)?;
+        let mut balances: HashMap<Uuid, i64> = HashMap::new();
+
+        for transaction in transactions {
+            for (entity_id, net) in self.transaction_nets(transaction) {
+                *balances.entry(self.party(entity_id)).or_insert(0) += net;
+            }
+        }
+
+        Ok(to_money(balances, currency))
+    }
+
+    fn get_user_share(&self, transaction: &Transaction, user_id: Uuid) -> Money {
+        self.transaction_shares(transaction)
+            .into_iter()
+            .filter(|(entity_id, _)| *entity_id == user_id)
+            .map(|(_, share)| share)
+            .reduce(|a, b| Money::new(a.minor_units() + b.minor_units(), a.currency()))
+            .unwrap_or_else(|| Money::zero(transaction.amount.currency()))
+    }
+
+    fn normalize_split_ratios(&self, amount: &Money, splits: Vec<Split>) -> Vec<Split> {
+        let weights = money::integer_weights(split_weights(amount, &splits).iter());
+        let total: i128 = weights.iter().sum();
+        if total == 0 {
+            return splits;
+        }
+
+        splits
+            .into_iter()
+            .zip(weights)
+            .map(|(split, weight)| Split {
+                entity_id: split.entity_id,
+                share: SplitShare::Ratio(Rational::new(weight, total)),
+            })
+            .collect()
+    }
+
+    fn calculate_settlements(&self, balances: HashMap<Uuid, Money>) -> Vec<Settlement> {
+        let mut by_currency: BTreeMap<String, Vec<(Uuid, i64)>> = BTreeMap::new();
+        for (entity_id, balance) in balances {
+            by_currency
+                .entry(balance.currency().to_string())
+                .or_default()
+                .push((entity_id, balance.minor_units()));
+        }
+
+        let mut settlements = Vec::new();
+        for (currency, balances) in by_currency {
+            // Largest amounts first, entity id as tie-break for stable results
+            let sorted = |mut entries: Vec<(Uuid, i64)>| {
+                entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                entries
+            };
+            let mut creditors = sorted(balances.iter().filter(|(_, b)| *b > 0).copied().collect());
+            let mut debtors = sorted(
+                balances
+                    .iter()
+                    .filter(|(_, b)| *b < 0)
+                    .map(|(id, b)| (*id, -b))
+                    .collect(),
+            );
+
+            let (mut i, mut j) = (0, 0);
+            while i < debtors.len() && j < creditors.len() {
+                let amount = debtors[i].1.min(creditors[j].1);
+                settlements.push(Settlement {
+                    from_entity: debtors[i].0,
+                    to_entity: creditors[j].0,
+                    amount: Money::new(amount, currency.clone()),
+                });
+                debtors[i].1 -= amount;
+                creditors[j].1 -= amount;
+                if debtors[i].1 == 0 {
+                    i += 1;
+                }
+                if creditors[j].1 == 0 {
+                    j += 1;
+                }
+            }
+        }
+
+        settlements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::{LineItem, Payer};
+    use toml::value::Datetime;
+
+    fn entity(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn expense(paid_by: Uuid, minor_units: i64, splits: &[(Uuid, Rational)]) -> Transaction {
+        Transaction {
+            id: Uuid::nil(),
+            description: String::from("test"),
+            kind: TransactionKind::Expense,
+            paid_by_entity: paid_by,
+            payers: Vec::new(),
+            amount: Money::new(minor_units, "EUR"),
+            transaction_datetime_rfc_3339: "2025-12-27T12:00:00Z".parse::<Datetime>().unwrap(),
+            line_items: Vec::new(),
+            tax: None,
+            tip: None,
+            category: None,
+            tags: Vec::new(),
+            split_ratios: splits
+                .iter()
+                .map(|(entity_id, ratio)| Split {
+                    entity_id: *entity_id,
+                    share: SplitShare::Ratio(*ratio),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_statistics() {
+        let half = Rational::new(1, 2);
+        let ledger_id = entity(100);
+        let splits = [(entity(1), half), (entity(2), half)];
+        let mut dinner = expense(entity(1), 3000, &splits);
+        dinner.category = Some(String::from("food"));
+        let mut hotel = expense(entity(2), 10000, &splits);
+        hotel.id = entity(10);
+        hotel.transaction_datetime_rfc_3339 = "2026-01-02T12:00:00Z".parse().unwrap();
+        let mut transfer = expense(entity(2), 500, &[]);
+        transfer.kind = TransactionKind::Transfer {
+            to_entity: entity(1),
+        };
+        let transactions = vec![dinner, hotel, transfer];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let all = sut
+            .calculate_statistics(ledger_id, None, None, Period::Month)
+            .unwrap()
+            .unwrap();
+        let december = sut
+            .calculate_statistics(
+                ledger_id,
+                None,
+                Some(Date {
+                    year: 2025,
+                    month: 12,
+                    day: 31,
+                }),
+                Period::Day,
+            )
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(all.total, Money::new(13000, "EUR"));
+        assert_eq!(
+            all.per_entity[&entity(1)],
+            EntityStatistics {
+                paid: Money::new(3000, "EUR"),
+                consumed: Money::new(6500, "EUR"),
+            }
+        );
+        assert_eq!(all.per_category[&None], Money::new(10000, "EUR"));
+        assert_eq!(
+            all.per_period.keys().collect::<Vec<_>>(),
+            vec!["2025-12", "2026-01"]
+        );
+        assert_eq!(all.largest_expenses[0].id, entity(10));
+        assert_eq!(december.total, Money::new(3000, "EUR"));
+        assert_eq!(december.per_period["2025-12-27"], Money::new(3000, "EUR"));
+    }
+
+    #[test]
+    fn test_statistics_of_empty_ledger() {
+        let ledger_id = entity(100);
+        let sut = BalanceEngine::new().ledger(ledger_id, &[]);
+
+        let statistics = sut
+            .calculate_statistics(ledger_id, None, None, Period::Day)
+            .unwrap();
+
+        assert!(statistics.is_none());
+    }
+
+    #[test]
+    fn test_statistics_of_mixed_currencies() {
+        let ledger_id = entity(100);
+        let splits = [(entity(1), Rational::new(1, 1))];
+        let mut dollars = expense(entity(1), 1000, &splits);
+        dollars.id = entity(10);
+        dollars.amount = Money::new(1000, "USD");
+        let transactions = vec![expense(entity(1), 1000, &splits), dollars];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let statistics = sut
+            .calculate_statistics(ledger_id, None, None, Period::Day)
+            .unwrap();
+
+        assert!(statistics.is_none());
+    }
+
+    #[test]
+    fn test_balance_history_is_chronological() {
+        let half = Rational::new(1, 2);
+        let ledger_id = entity(100);
+        let splits = [(entity(1), half), (entity(2), half)];
+        let mut later = expense(entity(2), 1000, &splits);
+        later.transaction_datetime_rfc_3339 = "2025-12-28T09:00:00+01:00".parse().unwrap();
+        let earlier = expense(entity(1), 400, &splits);
+        let transactions = vec![later, earlier];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let history = sut.balance_history(ledger_id).unwrap();
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].balances[&entity(1)], Money::new(200, "EUR"));
+        assert_eq!(history[1].balances[&entity(1)], Money::new(-300, "EUR"));
+        let net = sut.calculate_net_balances(ledger_id).unwrap();
+        assert_eq!(history[1].balances[&entity(2)], net[&entity(2)]);
+    }
+
+    #[test]
+    fn test_balance_impact_of_changes() {
+        let half = Rational::new(1, 2);
+        let splits = [(entity(1), half), (entity(2), half)];
+        let changes = vec![
+            // Added: entity 1 owes 5.00
+            TransactionChange {
+                before: None,
+                after: Some(expense(entity(2), 1000, &splits)),
+            },
+            // Edited from 4.00 to 6.00 paid by entity 1: it is owed 1.00 more
+            TransactionChange {
+                before: Some(expense(entity(1), 400, &splits)),
+                after: Some(expense(entity(1), 600, &splits)),
+            },
+        ];
+        let sut = BalanceEngine::new();
+
+        assert_eq!(
+            sut.balance_impact(&changes, entity(1)).unwrap(),
+            Some(Money::new(-400, "EUR"))
+        );
+        assert_eq!(sut.balance_impact(&[], entity(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_three_way_split_sums_to_amount() {
+        let third = Rational::new(1, 3);
+        let txn = expense(
+            entity(1),
+            1000,
+            &[(entity(3), third), (entity(2), third), (entity(1), third)],
+        );
+        let sut = BalanceEngine::new();
+
+        let shares: i64 = sut
+            .transaction_shares(&txn)
+            .iter()
+            .map(|(_, share)| share.minor_units())
+            .sum();
+
+        assert_eq!(shares, 1000);
+        assert_eq!(sut.get_user_share(&txn, entity(1)), Money::new(334, "EUR"));
+        assert_eq!(sut.get_user_share(&txn, entity(2)), Money::new(333, "EUR"));
+        assert_eq!(sut.get_user_share(&txn, entity(4)), Money::zero("EUR"));
+    }
+
+    #[test]
+    fn test_net_balances_sum_to_zero() {
+        let third = Rational::new(1, 3);
+        let ledger_id = entity(100);
+        let splits = [(entity(1), third), (entity(2), third), (entity(3), third)];
+        let transactions = vec![
+            expense(entity(1), 1000, &splits),
+            expense(entity(2), 2000, &splits),
+        ];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let balances = sut.calculate_net_balances(ledger_id).unwrap();
+
+        // 1000 splits into 334/333/333 and 2000 into 667/667/666
+        assert_eq!(balances[&entity(1)], Money::new(1000 - 334 - 667, "EUR"));
+        assert_eq!(balances[&entity(2)], Money::new(2000 - 333 - 667, "EUR"));
+        assert_eq!(balances[&entity(3)], Money::new(-333 - 666, "EUR"));
+        let total: i64 = balances.values().map(Money::minor_units).sum();
+        assert_eq!(total, 0);
+    }
+
+    #[test]
+    fn test_balances_from_user_perspective() {
+        let half = Rational::new(1, 2);
+        let ledger_id = entity(100);
+        let transactions = vec![
+            expense(entity(1), 1001, &[(entity(1), half), (entity(2), half)]),
+            expense(entity(2), 400, &[(entity(1), half), (entity(2), half)]),
+        ];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let balances = sut.calculate_balances(ledger_id, entity(1)).unwrap();
+
+        // 10.01 splits into 5.01 for entity 1 and 5.00 for entity 2
+        assert_eq!(balances[&entity(2)], Money::new(500 - 200, "EUR"));
+    }
+
+    #[test]
+    fn test_multiple_payers() {
+        let third = Rational::new(1, 3);
+        let ledger_id = entity(100);
+        let mut hotel = expense(
+            entity(1),
+            30000,
+            &[(entity(1), third), (entity(2), third), (entity(3), third)],
+        );
+        hotel.payers = vec![
+            Payer {
+                entity_id: entity(1),
+                amount: Money::new(20000, "EUR"),
+            },
+            Payer {
+                entity_id: entity(2),
+                amount: Money::new(10000, "EUR"),
+            },
+        ];
+        let transactions = vec![hotel];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let net = sut.calculate_net_balances(ledger_id).unwrap();
+        let balances = sut.calculate_balances(ledger_id, entity(3)).unwrap();
+
+        assert_eq!(net[&entity(1)], Money::new(10000, "EUR"));
+        assert_eq!(net[&entity(2)], Money::new(0, "EUR"));
+        assert_eq!(net[&entity(3)], Money::new(-10000, "EUR"));
+        assert_eq!(balances[&entity(1)], Money::new(-10000, "EUR"));
+        assert!(!balances.contains_key(&entity(2)));
+    }
+
+    #[test]
+    fn test_transfer_settles_debt() {
+        let half = Rational::new(1, 2);
+        let ledger_id = entity(100);
+        let mut repayment = expense(entity(2), 500, &[]);
+        repayment.kind = TransactionKind::Transfer {
+            to_entity: entity(1),
+        };
+        let transactions = vec![
+            expense(entity(1), 1000, &[(entity(1), half), (entity(2), half)]),
+            repayment,
+        ];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let net = sut.calculate_net_balances(ledger_id).unwrap();
+        let balances = sut.calculate_balances(ledger_id, entity(1)).unwrap();
+
+        assert!(net.values().all(Money::is_zero));
+        assert_eq!(balances[&entity(2)], Money::zero("EUR"));
+        assert!(
+            sut.calculate_settlements(net).is_empty(),
+            "nothing left to settle"
+        );
+    }
+
+    #[test]
+    fn test_refund_follows_original_shares() {
+        let ledger_id = entity(100);
+        let mut hotel = expense(
+            entity(1),
+            3000,
+            &[
+                (entity(1), Rational::new(1, 3)),
+                (entity(2), Rational::new(2, 3)),
+            ],
+        );
+        hotel.id = entity(50);
+        // entity 2 receives the refund, the split ratios are ignored
+        let mut refund = expense(entity(2), -600, &[(entity(1), Rational::new(1, 1))]);
+        refund.kind = TransactionKind::Refund {
+            original_transaction_id: Some(hotel.id),
+        };
+        let transactions = vec![hotel, refund.clone()];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let net = sut.calculate_net_balances(ledger_id).unwrap();
+
+        assert_eq!(
+            sut.get_user_share(&refund, entity(1)),
+            Money::new(-200, "EUR")
+        );
+        assert_eq!(net[&entity(1)], Money::new(2200, "EUR"));
+        assert_eq!(net[&entity(2)], Money::new(-2200, "EUR"));
+    }
+
+    #[test]
+    fn test_income_without_original_uses_splits() {
+        let half = Rational::new(1, 2);
+        let mut income = expense(entity(1), -1000, &[(entity(1), half), (entity(2), half)]);
+        income.kind = TransactionKind::Refund {
+            original_transaction_id: None,
+        };
+
+        let nets = BalanceEngine::new().transaction_nets(&income);
+
+        // entity 1 holds the money and owes entity 2 its half
+        assert_eq!(nets[&entity(1)], -500);
+        assert_eq!(nets[&entity(2)], 500);
+    }
+
+    #[test]
+    fn test_households_net_as_one_party() {
+        let third = Rational::new(1, 3);
+        let ledger_id = entity(100);
+        let splits = [(entity(1), third), (entity(2), third), (entity(3), third)];
+        let transactions = vec![expense(entity(1), 900, &splits)];
+        let couple = Household {
+            id: entity(12),
+            display_name: String::from("1 & 2"),
+            members: vec![entity(1), entity(2)],
+        };
+        let sut = BalanceEngine::new()
+            .ledger(ledger_id, &transactions)
+            .households(&[couple]);
+
+        let net = sut.calculate_net_balances(ledger_id).unwrap();
+        let balances = sut.calculate_balances(ledger_id, entity(2)).unwrap();
+        let consumption = sut.calculate_consumption(ledger_id).unwrap();
+
+        assert_eq!(net.len(), 2);
+        assert_eq!(net[&entity(12)], Money::new(300, "EUR"));
+        assert_eq!(balances[&entity(3)], Money::new(300, "EUR"));
+        assert_eq!(consumption[&entity(2)], Money::new(300, "EUR"));
+        let settlements = sut.calculate_settlements(net);
+        assert_eq!(settlements.len(), 1);
+        assert_eq!(settlements[0].to_entity, entity(12));
+    }
+
+    #[test]
+    fn test_pot_pays_back_what_it_holds() {
+        let third = Rational::new(1, 3);
+        let ledger_id = entity(100);
+        let pot = entity(9);
+        let contribution = |member| {
+            let mut transfer = expense(member, 5000, &[]);
+            transfer.kind = TransactionKind::Transfer { to_entity: pot };
+            transfer
+        };
+        let transactions = vec![
+            contribution(entity(1)),
+            contribution(entity(2)),
+            contribution(entity(3)),
+            expense(
+                pot,
+                9000,
+                &[(entity(1), third), (entity(2), third), (entity(3), third)],
+            ),
+        ];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        let net = sut.calculate_net_balances(ledger_id).unwrap();
+        let balances = sut.calculate_balances(ledger_id, entity(1)).unwrap();
+
+        assert_eq!(net[&pot], Money::new(-6000, "EUR"), "the pot holds 60");
+        assert_eq!(balances[&pot], Money::new(2000, "EUR"));
+        let settlements = sut.calculate_settlements(net);
+        assert_eq!(settlements.len(), 3);
+        assert!(settlements.iter().all(|s| s.from_entity == pot));
+    }
+
+    #[test]
+    fn test_group_settlement_across_ledgers() {
+        let half = Rational::new(1, 2);
+        let (trip, flat) = (entity(100), entity(101));
+        // 2 owes 1 five on the trip, 1 owes 2 three in the flat
+        let trip_transactions = vec![expense(
+            entity(1),
+            1000,
+            &[(entity(1), half), (entity(2), half)],
+        )];
+        let flat_transactions = vec![expense(
+            entity(2),
+            600,
+            &[(entity(1), half), (entity(2), half)],
+        )];
+        let sut = BalanceEngine::new()
+            .ledger(trip, &trip_transactions)
+            .ledger(flat, &flat_transactions);
+
+        let group = sut.calculate_group_net_balances().unwrap();
+        let settlements = sut.calculate_settlements(group["EUR"].clone());
+        let per_ledger = sut.ledger_settlements("EUR").unwrap();
+
+        assert_eq!(group["EUR"][&entity(1)], Money::new(200, "EUR"));
+        assert_eq!(settlements.len(), 1);
+        assert_eq!(settlements[0].from_entity, entity(2));
+        assert_eq!(settlements[0].amount, Money::new(200, "EUR"));
+        assert_eq!(per_ledger.len(), 2);
+        assert_eq!(per_ledger[0].0, trip);
+        assert_eq!(per_ledger[0].1.from_entity, entity(2));
+        assert_eq!(per_ledger[1].1.from_entity, entity(1));
+    }
+
+    #[test]
+    fn test_mixed_currencies_are_rejected() {
+        let ledger_id = entity(100);
+        let mut chf = expense(entity(1), 100, &[(entity(1), Rational::new(1, 1))]);
+        chf.amount = Money::new(100, "CHF");
+        let transactions = vec![
+            expense(entity(1), 100, &[(entity(1), Rational::new(1, 1))]),
+            chf,
+        ];
+        let sut = BalanceEngine::new().ledger(ledger_id, &transactions);
+
+        assert!(matches!(
+            sut.calculate_net_balances(ledger_id),
+            Err(BusinessLogicError::CurrencyMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_settlements_clear_all_balances() {
+        let balances = HashMap::from([
+            (entity(1), Money::new(1500, "EUR")),
+            (entity(2), Money::new(-1000, "EUR")),
+            (entity(3), Money::new(-500, "EUR")),
+        ]);
+
+        let sut = BalanceEngine::new().calculate_settlements(balances);
+
+        assert_eq!(sut.len(), 2);
+        assert_eq!(sut[0].from_entity, entity(2));
+        assert_eq!(sut[0].to_entity, entity(1));
+        assert_eq!(sut[0].amount, Money::new(1000, "EUR"));
+        assert_eq!(sut[1].from_entity, entity(3));
+        assert_eq!(sut[1].amount, Money::new(500, "EUR"));
+    }
+
+    fn splits(shares: Vec<SplitShare>) -> Vec<Split> {
+        shares
+            .into_iter()
+            .enumerate()
+            .map(|(i, share)| Split {
+                entity_id: entity(i as u128 + 1),
+                share,
+            })
+            .collect()
+    }
+
+    fn shares_of(amount: i64, shares: Vec<SplitShare>) -> Vec<i64> {
+        let mut txn = expense(entity(1), amount, &[]);
+        txn.split_ratios = splits(shares);
+        BalanceEngine::new()
+            .transaction_shares(&txn)
+            .iter()
+            .map(|(_, share)| share.minor_units())
+            .collect()
+    }
+
+    #[test]
+    fn test_exact_amounts_are_kept() {
+        let sut = shares_of(
+            1000,
+            vec![
+                SplitShare::Exact(Money::new(125, "EUR")),
+                SplitShare::Exact(Money::new(875, "EUR")),
+            ],
+        );
+
+        assert_eq!(sut, vec![125, 875]);
+    }
+
+    #[test]
+    fn test_percentages() {
+        let sut = shares_of(
+            1000,
+            vec![
+                SplitShare::Percentage(Rational::new(25, 1)),
+                SplitShare::Percentage(Rational::new(75, 1)),
+            ],
+        );
+
+        assert_eq!(sut, vec![250, 750]);
+    }
+
+    #[test]
+    fn test_integer_shares() {
+        let sut = shares_of(
+            900,
+            vec![
+                SplitShare::Shares(2),
+                SplitShare::Shares(1),
+                SplitShare::Shares(0),
+            ],
+        );
+
+        assert_eq!(sut, vec![600, 300, 0]);
+    }
+
+    #[test]
+    fn test_equal_plus_adjustment() {
+        // 60.00 with 4.50 extra for the first person: 18.50 each plus 4.50
+        let sut = shares_of(
+            6000,
+            vec![
+                SplitShare::Adjustment(Money::new(450, "EUR")),
+                SplitShare::Adjustment(Money::zero("EUR")),
+                SplitShare::Adjustment(Money::zero("EUR")),
+            ],
+        );
+
+        assert_eq!(sut, vec![2300, 1850, 1850]);
+    }
+
+    #[test]
+    fn test_line_items_with_tax_and_tip() {
+        let mut txn = expense(entity(1), 3600, &[]);
+        txn.tax = Some(Money::new(250, "EUR"));
+        txn.tip = Some(Money::new(350, "EUR"));
+        txn.line_items = vec![
+            LineItem {
+                description: String::from("Margherita"),
+                amount: Money::new(1200, "EUR"),
+                splits: splits(vec![SplitShare::Shares(1)]),
+            },
+            LineItem {
+                description: String::from("Wine"),
+                amount: Money::new(1800, "EUR"),
+                splits: splits(vec![SplitShare::Shares(1), SplitShare::Shares(1)]),
+            },
+        ];
+
+        let sut = BalanceEngine::new().transaction_shares(&txn);
+
+        // entity 1 had 21.00 and entity 2 9.00 of the 30.00 items,
+        // so they carry 70% and 30% of the 6.00 tax and tip
+        assert_eq!(
+            sut,
+            vec![
+                (entity(1), Money::new(2100 + 420, "EUR")),
+                (entity(2), Money::new(900 + 180, "EUR")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_over_itemized_bill_has_no_negative_shares() {
+        let mut txn = expense(entity(1), 2000, &[]);
+        txn.tip = Some(Money::new(200, "EUR"));
+        txn.line_items = vec![
+            LineItem {
+                description: String::from("Margherita"),
+                amount: Money::new(1200, "EUR"),
+                splits: splits(vec![SplitShare::Shares(1)]),
+            },
+            LineItem {
+                description: String::from("Wine"),
+                amount: Money::new(1800, "EUR"),
+                splits: splits(vec![SplitShare::Shares(0), SplitShare::Shares(1)]),
+            },
+        ];
+
+        let sut = BalanceEngine::new().transaction_shares(&txn);
+
+        // 30.00 of items on a 20.00 bill: the bill is shared 40% to 60%
+        assert_eq!(
+            sut,
+            vec![
+                (entity(1), Money::new(800, "EUR")),
+                (entity(2), Money::new(1200, "EUR")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_split_ratios() {
+        let sut = BalanceEngine::new().normalize_split_ratios(
+            &Money::new(900, "EUR"),
+            splits(vec![SplitShare::Shares(2), SplitShare::Shares(1)]),
+        );
+
+        assert!(sut[0].share == SplitShare::Ratio(Rational::new(2, 3)));
+        assert!(sut[1].share == SplitShare::Ratio(Rational::new(1, 3)));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::migrations::FileKind;
+use crate::structs::{Group, Ledger, RecurringTemplate, Transaction};
+use crate::traits::PersistenceError;
+use serde::Serialize;
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, Value};
+
+/// Serialize a transaction, ledger or group to TOML in canonical form.
+///
+/// Keys are written in the order of the struct fields and numbers the way
+/// `toml` formats them, so the same data always gives the same text. If the
+/// file already exists, its comments and the formatting of values that did
+/// not change are kept: editing one field changes one line.
+pub fn to_string<T: Serialize>(
+    value: &T,
+    existing: Option<&str>,
+) -> Result<String, PersistenceError> {
+    let mut document: DocumentMut = toml::to_string(value)?
+        .parse()
+        .map_err(|e| PersistenceError::Toml(format!("{}", e)))?;
+
+    // A file that no longer parses is simply replaced
+    if let Some(existing) = existing.and_then(|text| text.parse::<DocumentMut>().ok()) {
+        merge_table(document.as_table_mut(), existing.as_table());
+        document.set_trailing(existing.trailing().clone());
+    }
+    Ok(document.to_string())
+}
+
+/// Write the text of a stored file of `kind` in canonical form, keeping the
+/// comments and formatting of `existing`.
+pub fn rewrite(kind: FileKind, text: &str, existing: &str) -> Result<String, PersistenceError> {
+    match kind {
+        FileKind::Group => to_string(&toml::from_str::<Group>(text)?, Some(existing)),
+        FileKind::Ledger => to_string(&toml::from_str::<Ledger>(text)?, Some(existing)),
+        FileKind::Transaction => to_string(&toml::from_str::<Transaction>(text)?, Some(existing)),
+        FileKind::RecurringTemplate => {
+            to_string(&toml::from_str::<RecurringTemplate>(text)?, Some(existing))
+        }
+    }
+}
+
+/// Copy comments and formatting from `existing` onto the keys of `table`
+/// that exist in both.
+fn merge_table(table: &mut Table, existing: &Table) {
+    *table.decor_mut() = existing.decor().clone();
+    for (mut key, item) in table.iter_mut() {
+        let Some((existing_key, existing_item)) = existing.get_key_value(key.get()) else {
+            continue;
+        };
+        *key.leaf_decor_mut() = existing_key.leaf_decor().clone();
+        match (item, existing_item) {
+            (Item::Value(value), Item::Value(existing)) => merge_value(value, existing),
+            (Item::Table(table), Item::Table(existing)) => merge_table(table, existing),
+            (Item::ArrayOfTables(tables), Item::ArrayOfTables(existing)) => {
+                for (table, existing) in tables.iter_mut().zip(existing.iter()) {
+                    merge_table(table, existing);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keep an unchanged value exactly as written, and the comments around a
+/// changed one.
+fn merge_value(value: &mut Value, existing: &Value) {
+    if same(value, existing) {
+        *value = existing.clone();
+        return;
+    }
+    *value.decor_mut() = existing.decor().clone();
+    match (value, existing) {
+        (Value::Array(array), Value::Array(existing)) => merge_array(array, existing),
+        (Value::InlineTable(table), Value::InlineTable(existing)) => {
+            merge_inline_table(table, existing)
+        }
+        _ => {}
+    }
+}
+
+fn merge_array(array: &mut Array, existing: &Array) {
+    for (value, existing) in array.iter_mut().zip(existing.iter()) {
+        merge_value(value, existing);
+    }
+}
+
+fn merge_inline_table(table: &mut InlineTable, existing: &InlineTable) {
+    for (mut key, value) in table.iter_mut() {
+        let Some((existing_key, Item::Value(existing_value))) = existing.get_key_value(key.get())
+        else {
+            continue;
+        };
+        *key.leaf_decor_mut() = existing_key.leaf_decor().clone();
+        merge_value(value, existing_value);
+    }
+}
+
+/// Whether two values are equal, regardless of how they are written
+/// (`12.5` and `12.50`, or `'a'` and `"a"`).
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| same(a, b)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSACTION: &str = r#"# Booked at the restaurant
+id = "019b5b3b-25e7-7e53-a0b6-0af3afde297c"
+description = "Dinner"
+paid_by_entity = "c8744a29-7ed0-447a-af5a-51e4ad291d1d"
+currency_iso_4217 = "EUR"
+amount = 60.00 # including tip
+transaction_datetime_rfc_3339 = 2025-12-27T12:00:00Z
+
+[[split_ratios]]
+entity_id = "c8744a29-7ed0-447a-af5a-51e4ad291d1d"
+shares = 1
+"#;
+
+    #[test]
+    fn test_unchanged_transaction_is_written_as_is() {
+        let transaction: Transaction = toml::from_str(TRANSACTION).unwrap();
+
+        let written = to_string(&transaction, Some(TRANSACTION)).unwrap();
+
+        assert_eq!(written, TRANSACTION);
+    }
+
+    #[test]
+    fn test_editing_one_field_changes_one_line() {
+        let mut transaction: Transaction = toml::from_str(TRANSACTION).unwrap();
+        transaction.description = String::from("Late dinner");
+
+        let written = to_string(&transaction, Some(TRANSACTION)).unwrap();
+
+        let changed: Vec<(&str, &str)> = TRANSACTION
+            .lines()
+            .zip(written.lines())
+            .filter(|(before, after)| before != after)
+            .collect();
+        assert_eq!(
+            changed,
+            vec![(
+                r#"description = "Dinner""#,
+                r#"description = "Late dinner""#
+            )]
+        );
+        assert_eq!(TRANSACTION.lines().count(), written.lines().count());
+    }
+
+    #[test]
+    fn test_output_does_not_depend_on_input_order() {
+        let ledger = r#"
+            participants = ["c8744a29-7ed0-447a-af5a-51e4ad291d1d"]
+            display_name = "Trip"
+            id = "019b5b3b-25e7-7e53-a0b6-0af3afde297c"
+        "#;
+        let group = r#"
+            [[entities]]
+            display_name = "Ana"
+            id = "c8744a29-7ed0-447a-af5a-51e4ad291d1d"
+        "#;
+
+        let ledger: Ledger = toml::from_str(ledger).unwrap();
+        let group: Group = toml::from_str(group).unwrap();
+        let ledger = to_string(&ledger, None).unwrap();
+        let group = to_string(&group, None).unwrap();
+
+        assert!(ledger.starts_with("id = "));
+        assert!(ledger.find("display_name") < ledger.find("participants"));
+        assert!(group.starts_with("schema_version = 0\n"));
+        assert!(group.find("id = ") < group.find("display_name"));
+        assert_eq!(
+            to_string(&toml::from_str::<Ledger>(&ledger).unwrap(), None).unwrap(),
+            ledger
+        );
+    }
+}
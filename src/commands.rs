@@ -1,5 +1,14 @@
+use crate::business_logic::{BalanceEngine, Period};
 use crate::components::{Header, LedgerHeader, Transaction};
-use crate::structs::AppState;
+use crate::dates;
+use crate::money::Money;
+use crate::recurring;
+use crate::structs::{AppState, Group, Transaction as StoredTransaction, TransactionKind};
+use crate::traits::{
+    BusinessLogic, PersistenceRepository, PointInTime, Revision, TransactionChange, Validator,
+};
+use crate::validation::GroupValidator;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 
 #[tauri::command]
@@ -55,64 +64,68 @@ pub fn render_ledger_header(state: tauri::State<AppState>) -> Result<String, Str
         .find(|l| l.id == ledger_uuid)
         .ok_or_else(|| "Selected ledger not found".to_string())?;
 
-    // Calculate per-user balances from all transactions
-    let mut balances: std::collections::HashMap<Uuid, f64> = std::collections::HashMap::new();
-    let mut currency = String::from("USD");
+    // Get the group entities to map UUIDs to names
+    let group = state.group.lock().map_err(|e| e.to_string())?;
+    let net_by_household = *state.net_by_household.lock().map_err(|e| e.to_string())?;
 
-    for txn in transactions.iter() {
-        // Use the currency from the first transaction
-        if currency == "USD" {
-            currency = txn.currency_iso_4217.clone();
+    // Calculate per-user (or per-household) balances from all transactions,
+    // separately for every currency the ledger uses
+    let by_currency = transactions_by_currency(&transactions);
+    let mut balance_list: Vec<(String, Money)> = Vec::new();
+    let mut pots: Vec<(String, Money, Money)> = Vec::new();
+    let mut own_consumption = None;
+    for currency_transactions in by_currency.values() {
+        let mut engine = BalanceEngine::new().ledger(ledger_uuid, currency_transactions);
+        if net_by_household {
+            engine = engine.households(&group.households);
         }
+        let balances = engine
+            .calculate_balances(ledger_uuid, user_uuid)
+            .map_err(|e| e.to_string())?;
 
-        // Calculate user's share
-        let user_ratio = txn
-            .split_ratios
-            .iter()
-            .find(|s| s.entity_id == user_uuid)
-            .map(|s| s.ratio.numerator() as f64 / s.ratio.denominator() as f64)
-            .unwrap_or(0.0);
-
-        let user_share = txn.amount * user_ratio;
-
-        // If user paid, they are owed money by others
-        if txn.paid_by_entity == user_uuid {
-            // User is owed by each person in the split
-            for split in &txn.split_ratios {
-                if split.entity_id != user_uuid {
-                    let other_share = txn.amount
-                        * (split.ratio.numerator() as f64 / split.ratio.denominator() as f64);
-                    *balances.entry(split.entity_id).or_insert(0.0) += other_share;
-                }
-            }
-        } else if txn.paid_by_entity != user_uuid {
-            // User owes money to the person who paid
-            *balances.entry(txn.paid_by_entity).or_insert(0.0) -= user_share;
+        // What the user consumed themselves, also when netting per household;
+        // a single amount only makes sense in a single currency
+        if by_currency.len() == 1 {
+            own_consumption = engine
+                .calculate_consumption(ledger_uuid)
+                .map_err(|e| e.to_string())?
+                .remove(&user_uuid);
         }
-    }
 
-    // Get the group entities to map UUIDs to names
-    let group = state.group.lock().map_err(|e| e.to_string())?;
-
-    // Convert HashMap to Vec of (name, amount) pairs, filtering out the current user
-    let mut balance_list: Vec<(String, f64)> = balances
-        .into_iter()
-        .filter(|(_, amount)| amount.abs() > 0.01) // Filter out near-zero balances
-        .filter_map(|(entity_id, amount)| {
+        // Pots are shown separately: what they hold and the user's stake in it
+        let net_balances = engine
+            .calculate_net_balances(ledger_uuid)
+            .map_err(|e| e.to_string())?;
+        pots.extend(
             group
                 .entities
                 .iter()
-                .find(|e| e.id == entity_id)
-                .map(|e| (e.display_name.clone(), amount))
-        })
-        .collect();
+                .filter(|e| e.is_pot())
+                .filter_map(|pot| {
+                    let holds = net_balances.get(&pot.id)?.negated();
+                    let stake = balances
+                        .get(&pot.id)
+                        .cloned()
+                        .unwrap_or_else(|| Money::zero(holds.currency()));
+                    Some((pot.display_name.clone(), holds, stake))
+                }),
+        );
+
+        // Convert HashMap to Vec of (name, amount) pairs, filtering out the current user
+        balance_list.extend(
+            balances
+                .into_iter()
+                .filter(|(id, amount)| !amount.is_zero() && !group.is_pot(*id))
+                .filter_map(|(id, amount)| {
+                    group
+                        .display_name(id)
+                        .map(|name| (name.to_string(), amount))
+                }),
+        );
+    }
 
     // Sort by absolute amount descending
-    balance_list.sort_by(|a, b| {
-        b.1.abs()
-            .partial_cmp(&a.1.abs())
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+    balance_list.sort_by_key(|(_, amount)| std::cmp::Reverse(amount.minor_units().abs()));
 
     // Collect all available ledgers for the dropdown
     let available_ledgers: Vec<(String, String)> = ledgers
@@ -120,16 +133,41 @@ pub fn render_ledger_header(state: tauri::State<AppState>) -> Result<String, Str
         .map(|l| (l.id.to_string(), l.display_name.clone()))
         .collect();
 
-    let header = LedgerHeader::new()
+    let mut header = LedgerHeader::new()
         .ledger_name(&ledger.display_name)
         .balances(balance_list)
-        .currency(&currency)
-        .ledgers(available_ledgers)
-        .build();
+        .pots(pots)
+        .ledgers(available_ledgers);
+    if let Some(consumption) = own_consumption {
+        header = header.own_consumption(consumption);
+    }
+    if !group.households.is_empty() {
+        header = header.household_netting(net_by_household);
+    }
+    if let Some(revision) = state
+        .viewed_revision
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+    {
+        header = header.viewing_past(revision_label(revision, &group));
+    }
+    let header = header.build();
 
     Ok(header)
 }
 
+/// Switch between netting balances per entity and per household.
+#[tauri::command]
+pub fn toggle_household_netting(state: tauri::State<AppState>) -> Result<String, String> {
+    {
+        let mut net_by_household = state.net_by_household.lock().map_err(|e| e.to_string())?;
+        *net_by_household = !*net_by_household;
+    }
+
+    render_ledger_header(state)
+}
+
 #[tauri::command]
 pub fn switch_ledger(ledger_id: String, state: tauri::State<AppState>) -> Result<String, String> {
     // Parse the ledger_id as UUID and find the matching ledger
@@ -171,8 +209,42 @@ pub fn switch_ledger(ledger_id: String, state: tauri::State<AppState>) -> Result
     Ok(nav)
 }
 
+/// Render the current ledger's transactions, optionally only those of a
+/// category and/or with a tag.
 #[tauri::command]
-pub fn render_transactions(state: tauri::State<AppState>) -> Result<String, String> {
+pub fn render_transactions(
+    filter_category: Option<String>,
+    filter_tag: Option<String>,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    use crate::components::TransactionFilter;
+
+    // Added or edited since the user last looked, and changed without a
+    // verified signature; the list works without markers
+    let (unread, unverified) = {
+        let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+        let (_, changes) = unseen_changes(&state, persistence.as_ref()).unwrap_or_else(|e| {
+            eprintln!("failed to find unseen changes: {}", e);
+            (String::new(), Vec::new())
+        });
+        let unread: HashSet<Uuid> = changes
+            .iter()
+            .filter_map(|change| change.after.as_ref().map(|t| t.id))
+            .collect();
+
+        let group = state.group.lock().map_err(|e| e.to_string())?;
+        let ledger_id = *state.current_ledger_id.lock().map_err(|e| e.to_string())?;
+        let unverified = ledger_id
+            .map(|ledger_id| persistence.unverified_transactions(ledger_id, &group))
+            .transpose()
+            .unwrap_or_else(|e| {
+                eprintln!("failed to verify signatures: {}", e);
+                None
+            })
+            .unwrap_or_default();
+        (unread, unverified)
+    };
+
     let ledgers = state.ledgers.lock().map_err(|e| e.to_string())?;
     let group = state.group.lock().map_err(|e| e.to_string())?;
     let current_ledger_id = state.current_ledger_id.lock().map_err(|e| e.to_string())?;
@@ -181,6 +253,7 @@ pub fn render_transactions(state: tauri::State<AppState>) -> Result<String, Stri
     // Get current ledger and user from state
     let ledger_uuid = current_ledger_id.ok_or_else(|| "No ledger selected".to_string())?;
     let user_uuid = state.user_id;
+    let validator = GroupValidator::for_group(&group);
 
     // Find the ledger
     let ledger_with_txns = ledgers
@@ -188,27 +261,88 @@ pub fn render_transactions(state: tauri::State<AppState>) -> Result<String, Stri
         .find(|l| l.id == ledger_uuid)
         .ok_or_else(|| "Selected ledger not found".to_string())?;
 
+    // Broken files are still listed, but say what is wrong with them
+    for txn in transactions.iter() {
+        for error in validator
+            .validate_transaction(txn, ledger_with_txns, &group)
+            .errors
+        {
+            eprintln!("transaction {}: {}: {}", txn.id, error.field, error.message);
+        }
+    }
+
+    // Refunds need the ledger's transactions to find what they refund
+    let engine = BalanceEngine::new().ledger(ledger_uuid, transactions.as_slice());
+
+    // Empty form fields mean no filter
+    let category = filter_category.filter(|c| !c.is_empty());
+    let tag = filter_tag
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+
     let mut html = String::from(r#"<section id="expense-list" class="flex flex-col">"#);
+    if !group.categories.is_empty() || transactions.iter().any(|t| !t.tags.is_empty()) {
+        html.push_str(
+            &TransactionFilter::new()
+                .categories(category_options(&group))
+                .category(category.clone())
+                .tag(tag.clone())
+                .build(),
+        );
+    }
 
-    // Render each transaction
-    for txn in transactions.iter() {
-        // Find the payer's name
-        let payer_name = group
-            .entities
-            .iter()
-            .find(|e| e.id == txn.paid_by_entity)
-            .map(|e| e.display_name.clone())
-            .unwrap_or_else(|| "Unknown".to_string());
+    // The user's balance right after each transaction, in its currency
+    let mut balance_after: HashMap<Uuid, Money> = HashMap::new();
+    for currency_transactions in transactions_by_currency(&transactions).values() {
+        let history = BalanceEngine::new()
+            .ledger(ledger_uuid, currency_transactions)
+            .balance_history(ledger_uuid)
+            .map_err(|e| e.to_string())?;
+        for point in history {
+            let balance = point
+                .balances
+                .get(&user_uuid)
+                .cloned()
+                .unwrap_or_else(|| Money::zero(point.transaction.amount.currency()));
+            balance_after.insert(point.transaction.id, balance);
+        }
+    }
+
+    // Newest first, in the order of the balance history
+    let mut ordered: Vec<&StoredTransaction> = transactions.iter().collect();
+    ordered.sort_by_key(|txn| {
+        std::cmp::Reverse((dates::timestamp(&txn.transaction_datetime_rfc_3339), txn.id))
+    });
 
-        // Calculate user's share
-        let user_ratio = txn
-            .split_ratios
+    // Render each transaction
+    let filtered = ordered.into_iter().filter(|txn| {
+        category
+            .as_ref()
+            .is_none_or(|c| txn.category.as_ref() == Some(c))
+            && tag.as_ref().is_none_or(|t| txn.has_tag(t))
+    });
+    for txn in filtered {
+        // Find the payers' names
+        let payer_name = txn
+            .payments()
             .iter()
-            .find(|s| s.entity_id == user_uuid)
-            .map(|s| s.ratio.numerator() as f64 / s.ratio.denominator() as f64)
-            .unwrap_or(0.0);
+            .map(|(payer_id, _)| {
+                group
+                    .entities
+                    .iter()
+                    .find(|e| e.id == *payer_id)
+                    .map(|e| e.display_name.clone())
+                    .unwrap_or_else(|| "Unknown".to_string())
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
 
-        let user_share = txn.amount * user_ratio;
+        // What the user paid minus their share
+        let user_net = engine
+            .transaction_nets(txn)
+            .get(&user_uuid)
+            .copied()
+            .unwrap_or(0);
 
         // Format date
         let date = format!("{}", txn.transaction_datetime_rfc_3339);
@@ -219,22 +353,61 @@ pub fn render_transactions(state: tauri::State<AppState>) -> Result<String, Stri
             .expense_id(txn.id.to_string())
             .description(&txn.description)
             .payer_name(&payer_name)
-            .total_amount(txn.amount)
-            .currency(&txn.currency_iso_4217)
-            .date(date_short);
+            .total_amount(txn.amount.clone())
+            .date(date_short)
+            .line_item_count(txn.line_items.len());
 
-        // Determine if user borrowed or lent
-        if txn.paid_by_entity == user_uuid {
-            // User paid, so they lent money (user_share - amount)
-            let lent_amount = txn.amount - user_share;
-            if lent_amount > 0.01 {
-                transaction = transaction.lent(lent_amount);
-            }
-        } else {
-            // Someone else paid, user borrowed their share
-            if user_share > 0.01 {
-                transaction = transaction.borrowed(user_share);
+        match txn.kind {
+            TransactionKind::Transfer { to_entity } => {
+                let recipient_name = group
+                    .entities
+                    .iter()
+                    .find(|e| e.id == to_entity)
+                    .map(|e| e.display_name.clone())
+                    .unwrap_or_else(|| "Unknown".to_string());
+                transaction = transaction.transfer_to(recipient_name);
             }
+            TransactionKind::Refund { .. } => transaction = transaction.refund(),
+            TransactionKind::Expense => {}
+        }
+
+        if let Some(category) = txn.category.as_ref() {
+            // Unknown keys are still shown, just not translated
+            let name = group
+                .category(category)
+                .map(|c| c.display_name.clone())
+                .unwrap_or_else(|| category.clone());
+            transaction = transaction.category(name);
+        }
+
+        // Determine if user borrowed or lent
+        if user_net > 0 {
+            // User paid more than their share, so they lent money
+            transaction = transaction.lent(Money::new(user_net, txn.amount.currency()));
+        } else if user_net < 0 {
+            // User paid less than their share, so they borrowed
+            transaction = transaction.borrowed(Money::new(-user_net, txn.amount.currency()));
+        }
+
+        if unread.contains(&txn.id) {
+            transaction = transaction.unread();
+        }
+
+        if let Some((revision, signature)) = unverified.get(&txn.id) {
+            transaction = match validator.validate_signature(revision, signature) {
+                Err(e) => transaction.unverified(e.message, true),
+                Ok(()) => transaction.unverified(
+                    format!(
+                        "Changed by {} without a verified signature",
+                        revision.author
+                    ),
+                    false,
+                ),
+            };
+        }
+
+        if let Some(balance) = balance_after.get(&txn.id) {
+            transaction = transaction.balance_after(balance.clone());
         }
 
         html.push_str(&transaction.build());
@@ -249,6 +422,7 @@ pub fn render_transactions(state: tauri::State<AppState>) -> Result<String, Stri
 pub fn get_expense(expense_id: String, state: tauri::State<AppState>) -> Result<String, String> {
     use crate::components::ExpenseForm;
 
+    let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
     let ledgers = state.ledgers.lock().map_err(|e| e.to_string())?;
     let group = state.group.lock().map_err(|e| e.to_string())?;
     let current_ledger_id = state.current_ledger_id.lock().map_err(|e| e.to_string())?;
@@ -279,16 +453,792 @@ pub fn get_expense(expense_id: String, state: tauri::State<AppState>) -> Result<
         .collect();
 
     // Build the expense form
-    let form = ExpenseForm::new()
+    let mut form = ExpenseForm::new()
         .expense_id(expense_id)
         .description(&txn.description)
         .paid_by(&txn.paid_by_entity.to_string())
-        .amount(txn.amount)
-        .currency(&txn.currency_iso_4217)
+        .payers(txn.payers.clone())
+        .amount(txn.amount.clone())
         .date(txn.transaction_datetime_rfc_3339.to_string())
         .split_ratios(txn.split_ratios.clone())
+        .line_items(txn.line_items.clone())
+        .tax(txn.tax.clone())
+        .tip(txn.tip.clone())
+        .category(txn.category.clone())
+        .categories(category_options(&group))
+        .tags(txn.tags.clone())
+        .participants(participants);
+
+    if state
+        .viewed_revision
+        .lock()
+        .map_err(|e| e.to_string())?
+        .is_some()
+    {
+        form = form.read_only(true);
+    }
+
+    // The form is still usable if the history cannot be read
+    let history = persistence
+        .transaction_history(ledger_uuid, expense_uuid)
+        .unwrap_or_else(|e| {
+            eprintln!("failed to load history of {}: {}", expense_uuid, e);
+            Vec::new()
+        });
+    // History is newest first, so the last entry is the one that added the transaction
+    if let Some(added) = history.last() {
+        let (name, known) = author_name(&added.revision, &group);
+        form = form.added_by(name, known);
+    }
+    if let Some(edited) = history.first().filter(|_| history.len() > 1) {
+        let (name, known) = author_name(&edited.revision, &group);
+        form = form.last_edited_by(name, known);
+    }
+    form = form.history(
+        history
+            .into_iter()
+            .map(|entry| {
+                let changes = entry
+                    .changes
+                    .iter()
+                    .map(|change| {
+                        format!(
+                            "{}: {} → {}",
+                            change.field,
+                            change.old_value.as_deref().unwrap_or("–"),
+                            change.new_value.as_deref().unwrap_or("–")
+                        )
+                    })
+                    .collect();
+                (
+                    entry.revision.revision_id.clone(),
+                    revision_label(&entry.revision, &group),
+                    changes,
+                )
+            })
+            .collect(),
+    );
+
+    match txn.kind {
+        TransactionKind::Transfer { to_entity } => {
+            form = form.transfer_to(to_entity.to_string());
+        }
+        TransactionKind::Refund {
+            original_transaction_id,
+        } => {
+            // Only expenses can be refunded
+            let refundable = transactions
+                .iter()
+                .filter(|t| t.kind == TransactionKind::Expense)
+                .map(|t| (t.id.to_string(), t.description.clone()))
+                .collect();
+            form = form
+                .refund(original_transaction_id.map(|id| id.to_string()))
+                .refundable_transactions(refundable);
+        }
+        TransactionKind::Expense => {}
+    }
+
+    Ok(form.build())
+}
+
+#[tauri::command]
+pub fn new_expense(state: tauri::State<AppState>) -> Result<String, String> {
+    use crate::components::ExpenseForm;
+
+    let ledgers = state.ledgers.lock().map_err(|e| e.to_string())?;
+    let group = state.group.lock().map_err(|e| e.to_string())?;
+    let current_ledger_id = state.current_ledger_id.lock().map_err(|e| e.to_string())?;
+    let transactions = state.transactions.lock().map_err(|e| e.to_string())?;
+
+    let ledger_uuid = current_ledger_id.ok_or_else(|| "No ledger selected".to_string())?;
+    let ledger = ledgers
+        .iter()
+        .find(|l| l.id == ledger_uuid)
+        .ok_or_else(|| "Selected ledger not found".to_string())?;
+
+    // Default to the ledger's most recent currency
+    let currency = transactions
+        .iter()
+        .max_by_key(|t| t.transaction_datetime_rfc_3339.to_string())
+        .map(|t| t.amount.currency().to_string())
+        .unwrap_or_else(|| "EUR".to_string());
+
+    let participants: Vec<(String, String)> = group
+        .entities
+        .iter()
+        .map(|e| (e.id.to_string(), e.display_name.clone()))
+        .collect();
+
+    // Only split between the people who are around today
+    let today = dates::today();
+    let form = ExpenseForm::new()
+        .paid_by(state.user_id.to_string())
+        .amount(Money::zero(currency))
+        .date(today.to_string())
+        .split_ratios(
+            ledger
+                .default_splits(today)
+                .into_iter()
+                .filter(|split| !group.is_pot(split.entity_id))
+                .collect(),
+        )
+        .categories(category_options(&group))
         .participants(participants)
         .build();
 
     Ok(form)
 }
+
+/// Re-render the split section of the expense form with the splits of an
+/// accommodation, weighted by the nights each participant stayed.
+#[tauri::command]
+pub fn per_night_splits(
+    check_in: String,
+    check_out: String,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    use crate::components::SplitSection;
+
+    let ledgers = state.ledgers.lock().map_err(|e| e.to_string())?;
+    let group = state.group.lock().map_err(|e| e.to_string())?;
+    let current_ledger_id = state.current_ledger_id.lock().map_err(|e| e.to_string())?;
+
+    let ledger_uuid = current_ledger_id.ok_or_else(|| "No ledger selected".to_string())?;
+    let ledger = ledgers
+        .iter()
+        .find(|l| l.id == ledger_uuid)
+        .ok_or_else(|| "Selected ledger not found".to_string())?;
+
+    let check_in = dates::parse_date(&check_in).ok_or_else(|| "Invalid check-in".to_string())?;
+    let check_out = dates::parse_date(&check_out).ok_or_else(|| "Invalid check-out".to_string())?;
+
+    let participants: Vec<(String, String)> = group
+        .entities
+        .iter()
+        .map(|e| (e.id.to_string(), e.display_name.clone()))
+        .collect();
+
+    let section = SplitSection::new()
+        .splits(ledger.per_night_splits(check_in, check_out))
+        .participants(participants)
+        .build();
+
+    Ok(section)
+}
+
+#[tauri::command]
+pub fn new_line_item(currency: String, state: tauri::State<AppState>) -> Result<String, String> {
+    use crate::components::LineItemRow;
+
+    let group = state.group.lock().map_err(|e| e.to_string())?;
+
+    let participants: Vec<(String, String)> = group
+        .entities
+        .iter()
+        .map(|e| (e.id.to_string(), e.display_name.clone()))
+        .collect();
+
+    // Rows only need a key that is unique within the form
+    let row = LineItemRow::new()
+        .key(Uuid::new_v4().simple().to_string())
+        .amount(Money::zero(currency))
+        .participants(participants)
+        .build();
+
+    Ok(row)
+}
+
+/// Book all recurring expenses of the current ledger that are due by today.
+#[tauri::command]
+pub fn materialize_recurring(state: tauri::State<AppState>) -> Result<String, String> {
+    {
+        let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+        let current_ledger_id = state.current_ledger_id.lock().map_err(|e| e.to_string())?;
+        let ledger_uuid = current_ledger_id.ok_or_else(|| "No ledger selected".to_string())?;
+
+        recurring::materialize_due(persistence.as_ref(), ledger_uuid, dates::today())
+            .map_err(|e| e.to_string())?;
+
+        let mut transactions = state.transactions.lock().map_err(|e| e.to_string())?;
+        *transactions = persistence
+            .list_transactions(ledger_uuid)
+            .map_err(|e| e.to_string())?;
+    }
+
+    render_transactions(None, None, state)
+}
+
+/// Spending statistics of the current ledger, optionally limited to a date
+/// range and grouped per day (default) or per month.
+#[tauri::command]
+pub fn render_statistics(
+    from: Option<String>,
+    to: Option<String>,
+    period: Option<String>,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    use crate::components::{BarChart, DonutChart, LineChart, Statistics};
+
+    let group = state.group.lock().map_err(|e| e.to_string())?;
+    let current_ledger_id = state.current_ledger_id.lock().map_err(|e| e.to_string())?;
+    let transactions = state.transactions.lock().map_err(|e| e.to_string())?;
+
+    let ledger_uuid = current_ledger_id.ok_or_else(|| "No ledger selected".to_string())?;
+
+    // Empty or invalid dates leave the range open
+    let from = from.as_deref().and_then(dates::parse_date);
+    let to = to.as_deref().and_then(dates::parse_date);
+    let period = match period.as_deref() {
+        Some("month") => Period::Month,
+        _ => Period::Day,
+    };
+
+    let engine = BalanceEngine::new().ledger(ledger_uuid, transactions.as_slice());
+    let statistics = engine
+        .calculate_statistics(ledger_uuid, from, to, period)
+        .map_err(|e| e.to_string())?;
+
+    let name = |id: Uuid| group.display_name(id).unwrap_or("Unknown").to_string();
+
+    let mut view = Statistics::new()
+        .range(
+            from.map(|d| d.to_string()).unwrap_or_default(),
+            to.map(|d| d.to_string()).unwrap_or_default(),
+        )
+        .monthly(period == Period::Month);
+    let Some(statistics) = statistics.filter(|s| !s.per_entity.is_empty()) else {
+        return Ok(view.build());
+    };
+
+    let mut people: Vec<(String, Money, Money)> = statistics
+        .per_entity
+        .iter()
+        .map(|(id, s)| (name(*id), s.paid.clone(), s.consumed.clone()))
+        .collect();
+    people.sort_by_key(|(_, _, consumed)| std::cmp::Reverse(consumed.minor_units()));
+
+    let mut categories: Vec<(String, Money)> = statistics
+        .per_category
+        .iter()
+        .map(|(key, amount)| {
+            let category_name = match key {
+                Some(key) => group
+                    .category(key)
+                    .map(|c| c.display_name.clone())
+                    .unwrap_or_else(|| key.clone()),
+                None => String::from("Uncategorized"),
+            };
+            (category_name, amount.clone())
+        })
+        .collect();
+    categories.sort_by_key(|(_, amount)| std::cmp::Reverse(amount.minor_units()));
+
+    // Net balance of everyone after each transaction in the range
+    let history: Vec<_> = engine
+        .balance_history(ledger_uuid)
+        .unwrap_or_else(|e| {
+            eprintln!("failed to calculate the balance history: {}", e);
+            Vec::new()
+        })
+        .into_iter()
+        .filter(|point| {
+            point
+                .transaction
+                .transaction_datetime_rfc_3339
+                .date
+                .is_some_and(|date| {
+                    let day = dates::day_number(date);
+                    from.is_none_or(|from| day >= dates::day_number(from))
+                        && to.is_none_or(|to| day <= dates::day_number(to))
+                })
+        })
+        .collect();
+    let mut balance_chart = LineChart::new().title("Balances").labels(
+        history
+            .iter()
+            .map(|point| {
+                point
+                    .transaction
+                    .transaction_datetime_rfc_3339
+                    .date
+                    .map(|date| date.to_string())
+                    .unwrap_or_default()
+            })
+            .collect(),
+    );
+    if let Some(last) = history.last() {
+        for party in last.balances.keys() {
+            let currency = statistics.total.currency();
+            let points = history
+                .iter()
+                .map(|point| {
+                    point
+                        .balances
+                        .get(party)
+                        .cloned()
+                        .unwrap_or_else(|| Money::zero(currency))
+                })
+                .collect();
+            balance_chart = balance_chart.series(name(*party), points);
+        }
+    }
+
+    view = view
+        .chart(balance_chart.build())
+        .chart(
+            DonutChart::new()
+                .title("Per Category")
+                .slices(categories.clone())
+                .build(),
+        )
+        .chart(
+            BarChart::new()
+                .title("Paid vs Consumed")
+                .rows(people.clone())
+                .build(),
+        )
+        .total(statistics.total.clone())
+        .people(people)
+        .categories(categories)
+        .periods(statistics.per_period.clone().into_iter().collect())
+        .largest(
+            statistics
+                .largest_expenses
+                .iter()
+                .map(|t| (t.id.to_string(), t.description.clone(), t.amount.clone()))
+                .collect(),
+        );
+
+    Ok(view.build())
+}
+
+/// View the group as it was after a revision or at the end of a date,
+/// read-only, or the latest state again if neither is given.
+///
+/// Re-renders the ledger header and the transaction list.
+#[tauri::command]
+pub fn time_travel(
+    date: Option<String>,
+    revision: Option<String>,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    let date = match date.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(date) => {
+            Some(dates::parse_date(date).ok_or_else(|| format!("{} is not a date", date))?)
+        }
+        None => None,
+    };
+    let at = match (revision.filter(|r| !r.trim().is_empty()), date) {
+        (Some(revision), _) => Some(PointInTime::Revision(revision.trim().to_string())),
+        (None, Some(date)) => Some(PointInTime::Date(date)),
+        (None, None) => None,
+    };
+
+    {
+        let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+        let previous = state
+            .viewed_revision
+            .lock()
+            .map_err(|e| e.to_string())?
+            .as_ref()
+            .map(|revision| PointInTime::Revision(revision.revision_id.clone()));
+        let viewed = persistence.view_at(at).map_err(|e| e.to_string())?;
+
+        // The current ledger may not have existed back then
+        let current = *state.current_ledger_id.lock().map_err(|e| e.to_string())?;
+        let loaded = persistence.load_group().and_then(|group| {
+            let ledgers = persistence.list_ledgers()?;
+            let ledger_id = current
+                .filter(|id| ledgers.iter().any(|l| l.id == *id))
+                .or_else(|| ledgers.first().map(|l| l.id));
+            let transactions = match ledger_id {
+                Some(id) => persistence.list_transactions(id)?,
+                None => Vec::new(),
+            };
+            Ok((group, ledgers, ledger_id, transactions))
+        });
+
+        // If anything is missing, go back to what was shown before, which
+        // still matches the state
+        let (group, ledgers, ledger_id, transactions) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                if let Err(rollback) = persistence.view_at(previous) {
+                    eprintln!("failed to return to the previous view: {}", rollback);
+                }
+                return Err(e.to_string());
+            }
+        };
+
+        *state.group.lock().map_err(|e| e.to_string())? = group;
+        *state.ledgers.lock().map_err(|e| e.to_string())? = ledgers;
+        *state.transactions.lock().map_err(|e| e.to_string())? = transactions;
+        *state.current_ledger_id.lock().map_err(|e| e.to_string())? = ledger_id;
+        *state.viewed_revision.lock().map_err(|e| e.to_string())? = viewed;
+    }
+
+    let header = render_ledger_header(state.clone())?;
+    let transactions = render_transactions(None, None, state)?;
+    Ok(header + &transactions)
+}
+
+/// Restore a transaction of the current ledger as it was after a revision.
+#[tauri::command]
+pub fn restore_transaction(
+    expense_id: String,
+    revision: String,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    let expense_uuid = Uuid::parse_str(&expense_id).map_err(|e| e.to_string())?;
+    {
+        let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+        let ledger_uuid = state
+            .current_ledger_id
+            .lock()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "No ledger selected".to_string())?;
+
+        // The old version has to be valid in today's group, whose entities
+        // and ledger participants may have changed since
+        let restored = persistence
+            .transaction_at(ledger_uuid, expense_uuid, &revision)
+            .map_err(|e| e.to_string())?;
+        if let Some(restored) = restored {
+            let ledgers = state.ledgers.lock().map_err(|e| e.to_string())?;
+            let group = state.group.lock().map_err(|e| e.to_string())?;
+            let ledger = ledgers
+                .iter()
+                .find(|l| l.id == ledger_uuid)
+                .ok_or_else(|| "Selected ledger not found".to_string())?;
+            let errors = GroupValidator::for_group(&group)
+                .validate_transaction(&restored, ledger, &group)
+                .errors;
+            if !errors.is_empty() {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|error| format!("{}: {}", error.field, error.message))
+                    .collect();
+                return Err(format!("Cannot restore: {}", errors.join("; ")));
+            }
+        }
+
+        persistence
+            .restore_transaction(ledger_uuid, expense_uuid, &revision)
+            .map_err(|e| e.to_string())?;
+        reload_transactions(&state, persistence.as_ref(), ledger_uuid)?;
+    }
+
+    render_transactions(None, None, state)
+}
+
+/// Undo a change with a revert commit, e.g. an accidental edit or deletion.
+#[tauri::command]
+pub fn revert_revision(revision: String, state: tauri::State<AppState>) -> Result<String, String> {
+    {
+        let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+        let ledger_uuid = state
+            .current_ledger_id
+            .lock()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "No ledger selected".to_string())?;
+
+        persistence
+            .revert_revision(&revision)
+            .map_err(|e| e.to_string())?;
+        reload_transactions(&state, persistence.as_ref(), ledger_uuid)?;
+    }
+
+    render_transactions(None, None, state)
+}
+
+/// Replace the cached transactions with the ones of `ledger_id` in storage.
+fn reload_transactions(
+    state: &AppState,
+    persistence: &dyn PersistenceRepository,
+    ledger_id: Uuid,
+) -> Result<(), String> {
+    let transactions = persistence
+        .list_transactions(ledger_id)
+        .map_err(|e| e.to_string())?;
+    *state.transactions.lock().map_err(|e| e.to_string())? = transactions;
+    Ok(())
+}
+
+/// Changes to the current ledger since the user last looked, with the id of
+/// the revision they last saw.
+fn unseen_changes(
+    state: &AppState,
+    persistence: &dyn PersistenceRepository,
+) -> Result<(String, Vec<TransactionChange>), String> {
+    let ledger_uuid = state
+        .current_ledger_id
+        .lock()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "No ledger selected".to_string())?;
+
+    // Without a last seen revision everything counts as seen
+    let last_seen = match persistence
+        .last_seen_revision(state.user_id)
+        .map_err(|e| e.to_string())?
+    {
+        Some(revision_id) => revision_id,
+        None => {
+            let current = persistence.current_revision().map_err(|e| e.to_string())?;
+            return Ok((current.revision_id, Vec::new()));
+        }
+    };
+
+    let changes = persistence
+        .transaction_changes_since(ledger_uuid, &last_seen)
+        .map_err(|e| e.to_string())?;
+    Ok((last_seen, changes))
+}
+
+/// Summary of the transactions added, edited and deleted since the user last
+/// looked, and what that did to their balance.
+#[tauri::command]
+pub fn render_changes(state: tauri::State<AppState>) -> Result<String, String> {
+    use crate::components::ChangeSummary;
+
+    let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+    let (last_seen, changes) = unseen_changes(&state, persistence.as_ref())?;
+    let transactions = state.transactions.lock().map_err(|e| e.to_string())?;
+    let ledger_uuid = state
+        .current_ledger_id
+        .lock()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "No ledger selected".to_string())?;
+
+    // Refunds of existing transactions need the ledger to be split
+    let engine = BalanceEngine::new().ledger(ledger_uuid, transactions.as_slice());
+    let impact = engine
+        .balance_impact(&changes, state.user_id)
+        .map_err(|e| e.to_string())?;
+
+    let entry = |t: &StoredTransaction| (t.description.clone(), t.amount.clone());
+    let (mut added, mut edited, mut deleted) = (Vec::new(), Vec::new(), Vec::new());
+    for change in &changes {
+        match (&change.before, &change.after) {
+            (None, Some(after)) => added.push(entry(after)),
+            (Some(_), Some(after)) => edited.push(entry(after)),
+            (Some(before), None) => deleted.push(entry(before)),
+            (None, None) => {}
+        }
+    }
+
+    let mut summary = ChangeSummary::new()
+        .since(last_seen.chars().take(7).collect::<String>())
+        .added(added)
+        .edited(edited)
+        .deleted(deleted);
+    if let Some(impact) = impact {
+        summary = summary.balance_impact(impact);
+    }
+
+    Ok(summary.build())
+}
+
+/// Remember that the user has seen everything up to the current revision.
+#[tauri::command]
+pub fn mark_seen(state: tauri::State<AppState>) -> Result<String, String> {
+    {
+        let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+        let current = persistence.current_revision().map_err(|e| e.to_string())?;
+        persistence
+            .mark_seen(state.user_id, &current.revision_id)
+            .map_err(|e| e.to_string())?;
+    }
+
+    render_transactions(None, None, state)
+}
+
+/// Short description of a revision, e.g. "2025-03-01T10:00:00Z • a1b2c3d • Alice".
+fn revision_label(revision: &Revision, group: &Group) -> String {
+    let short_id: String = revision.revision_id.chars().take(7).collect();
+    let (author, _) = author_name(revision, group);
+    format!("{} • {} • {}", revision.time, short_id, author)
+}
+
+/// Name of the member who made a revision, or the raw commit author and
+/// `false` if the email belongs to no member of the group.
+fn author_name(revision: &Revision, group: &Group) -> (String, bool) {
+    match group.entity_by_email(&revision.email) {
+        Some(entity) => (entity.display_name.clone(), true),
+        None => (format!("{} <{}>", revision.author, revision.email), false),
+    }
+}
+
+/// The group's categories as (key, display_name) pairs for pickers.
+fn category_options(group: &Group) -> Vec<(String, String)> {
+    group
+        .categories
+        .iter()
+        .map(|c| (c.key.clone(), c.display_name.clone()))
+        .collect()
+}
+
+/// A ledger's transactions grouped by currency. Balances are only added up
+/// within a currency, so a ledger with several is calculated per currency.
+fn transactions_by_currency(
+    transactions: &[StoredTransaction],
+) -> BTreeMap<String, Vec<StoredTransaction>> {
+    let mut by_currency: BTreeMap<String, Vec<StoredTransaction>> = BTreeMap::new();
+    for transaction in transactions {
+        by_currency
+            .entry(transaction.amount.currency().to_string())
+            .or_default()
+            .push(transaction.clone());
+    }
+    by_currency
+}
+
+/// Load the transactions of every ledger, for group-wide calculations.
+fn load_all_transactions(state: &AppState) -> Result<Vec<(Uuid, Vec<StoredTransaction>)>, String> {
+    let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+    let ledgers = state.ledgers.lock().map_err(|e| e.to_string())?;
+
+    ledgers
+        .iter()
+        .map(|ledger| {
+            persistence
+                .list_transactions(ledger.id)
+                .map(|transactions| (ledger.id, transactions))
+                .map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+/// Net balances and settlements across all ledgers.
+#[tauri::command]
+pub fn render_group_overview(state: tauri::State<AppState>) -> Result<String, String> {
+    use crate::components::GroupOverview;
+
+    let all_transactions = load_all_transactions(&state)?;
+    let ledger_names: HashMap<Uuid, String> = state
+        .ledgers
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|ledger| (ledger.id, ledger.display_name.clone()))
+        .collect();
+    let group = state.group.lock().map_err(|e| e.to_string())?;
+
+    let engine = all_transactions
+        .iter()
+        .fold(BalanceEngine::new(), |engine, (ledger_id, transactions)| {
+            engine.ledger(*ledger_id, transactions)
+        });
+    let balances_by_currency = engine
+        .calculate_group_net_balances()
+        .map_err(|e| e.to_string())?;
+
+    let name = |id: Uuid| group.display_name(id).unwrap_or("Unknown").to_string();
+
+    let mut overview = GroupOverview::new().ledger_count(all_transactions.len());
+    for (currency, balances) in balances_by_currency {
+        let mut balance_list: Vec<(String, Money)> = balances
+            .iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(id, amount)| (name(*id), amount.clone()))
+            .collect();
+        if balance_list.is_empty() {
+            continue;
+        }
+        balance_list.sort_by_key(|(_, amount)| std::cmp::Reverse(amount.minor_units()));
+
+        let settlements = engine
+            .calculate_settlements(balances)
+            .into_iter()
+            .map(|s| (name(s.from_entity), name(s.to_entity), s.amount))
+            .collect();
+        // What "Record As Paid" writes, so nobody is surprised by the pairs
+        let transfers = engine
+            .ledger_settlements(&currency)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(ledger_id, s)| {
+                let ledger_name = ledger_names
+                    .get(&ledger_id)
+                    .cloned()
+                    .unwrap_or_else(|| "Unknown".to_string());
+                (
+                    ledger_name,
+                    name(s.from_entity),
+                    name(s.to_entity),
+                    s.amount,
+                )
+            })
+            .collect();
+
+        overview = overview.currency(currency, balance_list, settlements, transfers);
+    }
+
+    Ok(overview.build())
+}
+
+/// Record the group-wide settlement of a currency as paid.
+///
+/// Every ledger gets the transfers that settle it, as listed in the overview;
+/// per person they add up to the group-wide payments. All of them are stored
+/// in one change, so a failure records none.
+#[tauri::command]
+pub fn record_group_settlement(
+    currency: String,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    let all_transactions = load_all_transactions(&state)?;
+    let engine = all_transactions
+        .iter()
+        .fold(BalanceEngine::new(), |engine, (ledger_id, transactions)| {
+            engine.ledger(*ledger_id, transactions)
+        });
+    let settlements = engine
+        .ledger_settlements(&currency)
+        .map_err(|e| e.to_string())?;
+
+    {
+        let persistence = state.persistence.lock().map_err(|e| e.to_string())?;
+        let now = dates::now();
+        let transfers = settlements
+            .into_iter()
+            .map(|(ledger_id, settlement)| {
+                let transfer = StoredTransaction {
+                    id: Uuid::new_v4(),
+                    description: String::from("Group settlement"),
+                    kind: TransactionKind::Transfer {
+                        to_entity: settlement.to_entity,
+                    },
+                    paid_by_entity: settlement.from_entity,
+                    payers: Vec::new(),
+                    amount: settlement.amount,
+                    transaction_datetime_rfc_3339: now,
+                    split_ratios: Vec::new(),
+                    line_items: Vec::new(),
+                    tax: None,
+                    tip: None,
+                    category: None,
+                    tags: Vec::new(),
+                };
+                (ledger_id, transfer)
+            })
+            .collect();
+        persistence
+            .create_transactions(
+                transfers,
+                &format!("Record group settlement in {}", currency),
+            )
+            .map_err(|e| e.to_string())?;
+
+        // The current ledger may have been settled as well
+        let current_ledger_id = state.current_ledger_id.lock().map_err(|e| e.to_string())?;
+        if let Some(ledger_uuid) = *current_ledger_id {
+            let mut transactions = state.transactions.lock().map_err(|e| e.to_string())?;
+            *transactions = persistence
+                .list_transactions(ledger_uuid)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    render_group_overview(state)
+}
@@ -1,9 +1,10 @@
-use crate::structs::Split;
+use crate::money::Money;
+use crate::structs::{LineItem, Payer, Split, SplitMode};
 /// UI Components for Borrow Checker
 ///
 /// All components use the builder pattern for flexible construction
 /// and return HTML strings styled with Tailwind CSS classes.
-use maud::html;
+use maud::{PreEscaped, html};
 
 pub struct Header {
     current_ledger: Option<String>,
@@ -80,12 +81,18 @@ pub struct Transaction {
     expense_id: Option<String>,
     description: String,
     payer_name: String,
-    total_amount: f64,
-    currency: String,
+    recipient_name: Option<String>,
+    is_refund: bool,
+    category: Option<String>,
+    total_amount: Money,
     date: String,
+    line_item_count: usize,
     status_label: String,
     status_color: String,
-    user_amount: f64,
+    user_amount: Option<Money>,
+    balance_after: Option<Money>,
+    is_unread: bool,
+    unverified: Option<(String, bool)>, // (reason, rejected by policy) pair
 }
 
 impl Transaction {
@@ -94,12 +101,18 @@ impl Transaction {
             expense_id: None,
             description: String::new(),
             payer_name: String::new(),
-            total_amount: 0.0,
-            currency: String::from("USD"),
+            recipient_name: None,
+            is_refund: false,
+            category: None,
+            total_amount: Money::zero("USD"),
             date: String::new(),
+            line_item_count: 0,
             status_label: String::new(),
             status_color: String::new(),
-            user_amount: 0.0,
+            user_amount: None,
+            balance_after: None,
+            is_unread: false,
+            unverified: None,
         }
     }
 
@@ -118,13 +131,26 @@ impl Transaction {
         self
     }
 
-    pub fn total_amount(mut self, amount: f64) -> Self {
-        self.total_amount = amount;
+    /// Render as a transfer from the payer to `name` instead of an expense
+    pub fn transfer_to(mut self, name: impl Into<String>) -> Self {
+        self.recipient_name = Some(name.into());
+        self
+    }
+
+    /// Render as a refund or income received by the payer
+    pub fn refund(mut self) -> Self {
+        self.is_refund = true;
+        self
+    }
+
+    /// Show a badge with the display name of the transaction's category
+    pub fn category(mut self, name: impl Into<String>) -> Self {
+        self.category = Some(name.into());
         self
     }
 
-    pub fn currency(mut self, currency: impl Into<String>) -> Self {
-        self.currency = currency.into();
+    pub fn total_amount(mut self, amount: Money) -> Self {
+        self.total_amount = amount;
         self
     }
 
@@ -133,27 +159,61 @@ impl Transaction {
         self
     }
 
-    pub fn borrowed(mut self, amount: f64) -> Self {
+    pub fn line_item_count(mut self, count: usize) -> Self {
+        self.line_item_count = count;
+        self
+    }
+
+    pub fn borrowed(mut self, amount: Money) -> Self {
         self.status_label = String::from("YOU BORROWED");
         self.status_color = String::from("text-red-500");
-        self.user_amount = -amount;
+        self.user_amount = Some(amount.negated());
         self
     }
 
-    pub fn lent(mut self, amount: f64) -> Self {
+    pub fn lent(mut self, amount: Money) -> Self {
         self.status_label = String::from("YOU LENT");
         self.status_color = String::from("text-green-400");
-        self.user_amount = amount;
+        self.user_amount = Some(amount);
+        self
+    }
+
+    /// The user's net balance in the ledger right after this transaction
+    pub fn balance_after(mut self, balance: Money) -> Self {
+        self.balance_after = Some(balance);
+        self
+    }
+
+    /// Mark as added or changed since the user last looked
+    pub fn unread(mut self) -> Self {
+        self.is_unread = true;
+        self
+    }
+
+    /// Mark as changed without a verified signature, as a rejected change
+    /// if the group only accepts signed changes
+    pub fn unverified(mut self, reason: impl Into<String>, rejected: bool) -> Self {
+        self.unverified = Some((reason.into(), rejected));
         self
     }
 
     pub fn build(self) -> String {
         let expense_id = self.expense_id.unwrap_or_else(|| "unknown".to_string());
-        let amount_display = if self.user_amount < 0.0 {
-            format!("-{} {:.2}", self.currency, self.user_amount.abs())
-        } else {
-            format!("{} {:.2}", self.currency, self.user_amount)
+        let status_label = match (&self.recipient_name, &self.user_amount) {
+            (Some(_), Some(amount)) if amount.is_positive() => String::from("YOU SENT"),
+            (Some(_), Some(amount)) if amount.is_negative() => String::from("YOU RECEIVED"),
+            (None, Some(amount)) if self.is_refund && amount.is_positive() => {
+                String::from("YOU GET BACK")
+            }
+            (None, Some(amount)) if self.is_refund && amount.is_negative() => {
+                String::from("YOU PASS ON")
+            }
+            _ => self.status_label,
         };
+        let amount_display = self
+            .user_amount
+            .unwrap_or_else(|| Money::zero(self.total_amount.currency()))
+            .to_string();
 
         html! {
             button
@@ -170,21 +230,64 @@ impl Transaction {
                 // Main info
                 div class="relative z-10" {
                     h3 class="text-xl font-light mb-1" {
+                        @if self.is_unread {
+                            span class="inline-block w-2 h-2 rounded-full bg-orange-500 mr-2 align-middle" title="New since you last looked" {}
+                        }
+                        @if let Some((reason, rejected)) = &self.unverified {
+                            @if *rejected {
+                                span class="font-mono text-[0.65rem] text-red-400 border border-red-400/40 rounded px-1 mr-2 align-middle" title=(reason) {
+                                    "REJECTED"
+                                }
+                            } @else {
+                                span class="font-mono text-[0.65rem] text-zinc-400 border border-zinc-400/40 rounded px-1 mr-2 align-middle" title=(reason) {
+                                    "UNVERIFIED"
+                                }
+                            }
+                        }
+                        @if self.recipient_name.is_some() {
+                            span class="font-mono text-[0.65rem] text-sky-400 border border-sky-400/40 rounded px-1 mr-2 align-middle" {
+                                "TRANSFER"
+                            }
+                        }
+                        @if self.is_refund {
+                            span class="font-mono text-[0.65rem] text-emerald-400 border border-emerald-400/40 rounded px-1 mr-2 align-middle" {
+                                "REFUND"
+                            }
+                        }
+                        @if let Some(category) = &self.category {
+                            span class="font-mono text-[0.65rem] text-amber-400 border border-amber-400/40 rounded px-1 mr-2 align-middle uppercase" {
+                                (category)
+                            }
+                        }
                         (self.description)
                     }
                     span class="font-mono text-xs text-gray-400 uppercase" {
-                        "Paid by: " (self.payer_name) " • Total: " (self.currency) " " (format!("{:.2}", self.total_amount)) " • " (self.date)
+                        @if let Some(recipient_name) = &self.recipient_name {
+                            (self.payer_name) " → " (recipient_name) " • Amount: " (self.total_amount) " • " (self.date)
+                        } @else if self.is_refund {
+                            "Received by: " (self.payer_name) " • Total: " (self.total_amount) " • " (self.date)
+                        } @else {
+                            "Paid by: " (self.payer_name) " • Total: " (self.total_amount) " • " (self.date)
+                        }
+                        @if self.line_item_count > 0 {
+                            " • " (self.line_item_count) " items"
+                        }
                     }
                 }
 
                 // Status
                 div class="relative z-10 text-right mr-8 font-mono" {
                     span class="block text-[0.65rem] text-gray-500 uppercase" {
-                        (self.status_label)
+                        (status_label)
                     }
                     span class=(format!("text-lg {}", self.status_color)) {
                         (amount_display)
                     }
+                    @if let Some(balance) = &self.balance_after {
+                        span class="block text-[0.65rem] text-gray-500 uppercase" {
+                            "Balance " (balance)
+                        }
+                    }
                 }
 
                 // Chevron
@@ -206,9 +309,12 @@ impl Default for Transaction {
 
 pub struct LedgerHeader {
     ledger_name: String,
-    balances: Vec<(String, f64)>, // (user_name, amount) pairs
-    currency: String,
+    balances: Vec<(String, Money)>, // (user_name, amount) pairs
     ledgers: Vec<(String, String)>, // (id, name) pairs
+    own_consumption: Option<Money>,
+    household_netting: Option<bool>,
+    pots: Vec<(String, Money, Money)>, // (pot_name, holds, your_stake) triples
+    viewing_past: Option<String>,
 }
 
 impl LedgerHeader {
@@ -216,23 +322,45 @@ impl LedgerHeader {
         Self {
             ledger_name: String::new(),
             balances: Vec::new(),
-            currency: String::from("USD"),
             ledgers: Vec::new(),
+            own_consumption: None,
+            household_netting: None,
+            pots: Vec::new(),
+            viewing_past: None,
         }
     }
 
-    pub fn ledger_name(mut self, name: impl Into<String>) -> Self {
-        self.ledger_name = name.into();
+    /// Mark the ledger as an earlier, read-only state described by `label`
+    pub fn viewing_past(mut self, label: impl Into<String>) -> Self {
+        self.viewing_past = Some(label.into());
         self
     }
 
-    pub fn balances(mut self, balances: Vec<(String, f64)>) -> Self {
-        self.balances = balances;
+    /// Shared pots with the amount they hold and the user's stake in them
+    pub fn pots(mut self, pots: Vec<(String, Money, Money)>) -> Self {
+        self.pots = pots;
+        self
+    }
+
+    /// The user's own share of all expenses
+    pub fn own_consumption(mut self, amount: Money) -> Self {
+        self.own_consumption = Some(amount);
+        self
+    }
+
+    /// Show the "settle as household" toggle in the given state
+    pub fn household_netting(mut self, enabled: bool) -> Self {
+        self.household_netting = Some(enabled);
+        self
+    }
+
+    pub fn ledger_name(mut self, name: impl Into<String>) -> Self {
+        self.ledger_name = name.into();
         self
     }
 
-    pub fn currency(mut self, currency: impl Into<String>) -> Self {
-        self.currency = currency.into();
+    pub fn balances(mut self, balances: Vec<(String, Money)>) -> Self {
+        self.balances = balances;
         self
     }
 
@@ -245,6 +373,18 @@ impl LedgerHeader {
         html! {
             header class="px-12 py-8 flex justify-between items-end border-b border-zinc-700 bg-gradient-to-b from-zinc-900 to-transparent" id="ledger-header" {
                 div class="title-group" {
+                    @if let Some(label) = &self.viewing_past {
+                        div class="flex items-center gap-4 mb-4 px-3 py-2 border border-amber-400/60 bg-amber-400/10 rounded font-mono text-xs text-amber-400 uppercase" {
+                            span { "Read-only • As of " (label) }
+                            button
+                                class="underline hover:text-white transition-colors"
+                                type="button"
+                                hx-tauri-invoke="time_travel"
+                                hx-target="#main-content" {
+                                "Back To Today"
+                            }
+                        }
+                    }
                     span class="font-mono text-xs text-orange-500 uppercase tracking-wide" {
                         "LEDGER"
                     }
@@ -267,12 +407,107 @@ impl LedgerHeader {
                             }
                         }
                     }
+                    div class="flex gap-6" {
+                        // Nothing can be written while looking at the past
+                        @if self.viewing_past.is_none() {
+                            button
+                                class="font-mono text-xs text-zinc-500 uppercase hover:text-orange-500 transition-colors"
+                                type="button"
+                                hx-tauri-invoke="new_expense"
+                                hx-target="#expense-list" {
+                                "Add Expense"
+                            }
+                            button
+                                class="font-mono text-xs text-zinc-500 uppercase hover:text-orange-500 transition-colors"
+                                type="button"
+                                hx-tauri-invoke="materialize_recurring"
+                                hx-target="#expense-list" {
+                                "Book Recurring Expenses"
+                            }
+                        }
+                        button
+                            class="font-mono text-xs text-zinc-500 uppercase hover:text-orange-500 transition-colors"
+                            type="button"
+                            hx-tauri-invoke="render_group_overview"
+                            hx-target="#expense-list"
+                            hx-swap="outerHTML" {
+                            "All Ledgers"
+                        }
+                        button
+                            class="font-mono text-xs text-zinc-500 uppercase hover:text-orange-500 transition-colors"
+                            type="button"
+                            hx-tauri-invoke="render_statistics"
+                            hx-target="#expense-list"
+                            hx-swap="outerHTML" {
+                            "Statistics"
+                        }
+                        button
+                            class="font-mono text-xs text-zinc-500 uppercase hover:text-orange-500 transition-colors"
+                            type="button"
+                            hx-tauri-invoke="render_changes"
+                            hx-target="#expense-list"
+                            hx-swap="outerHTML" {
+                            "What Changed"
+                        }
+                        form
+                            class="flex items-center gap-2"
+                            hx-tauri-invoke="time_travel"
+                            hx-target="#main-content" {
+                            input
+                                type="date"
+                                name="date"
+                                aria-label="As of"
+                                required
+                                class="bg-transparent border-b border-zinc-700 font-mono text-xs text-zinc-500 focus:border-orange-500 focus:outline-none";
+                            button
+                                class="font-mono text-xs text-zinc-500 uppercase hover:text-orange-500 transition-colors"
+                                type="submit" {
+                                "View As Of"
+                            }
+                        }
+                    }
+                }
+
+                @if !self.pots.is_empty() {
+                    div class="pots text-right" {
+                        span class="font-mono text-xs text-gray-500 uppercase block mb-3" {
+                            "KITTY"
+                        }
+                        div class="space-y-2" {
+                            @for (pot_name, holds, stake) in &self.pots {
+                                div class="flex items-center justify-end gap-3" {
+                                    span class="text-sm text-gray-400" {
+                                        (pot_name)
+                                    }
+                                    span class="font-mono text-lg text-amber-400" {
+                                        (holds)
+                                    }
+                                }
+                                span class="font-mono text-xs text-gray-500 block" {
+                                    "Your stake: " (stake)
+                                }
+                            }
+                        }
+                    }
                 }
 
                 div class="balance text-right" {
                     span class="font-mono text-xs text-gray-500 uppercase block mb-3" {
                         "YOUR BALANCES"
                     }
+                    @if let Some(enabled) = self.household_netting {
+                        label class="flex items-center justify-end gap-2 font-mono text-xs text-zinc-500 uppercase mb-3 cursor-pointer" {
+                            input
+                                type="checkbox"
+                                checked[enabled]
+                                hx-tauri-invoke="toggle_household_netting"
+                                hx-trigger="change"
+                                hx-target="#ledger-header"
+                                hx-swap="outerHTML"
+                                class="w-3 h-3 accent-orange-500";
+                            "Settle as households"
+                        }
+                    }
                     @if self.balances.is_empty() {
                         span class="font-mono text-xl text-gray-600" {
                             "All settled up"
@@ -284,23 +519,28 @@ impl LedgerHeader {
                                     span class="text-sm text-gray-400" {
                                         (user_name)
                                     }
-                                    @if *amount < 0.0 {
+                                    @if amount.is_negative() {
                                         span class="font-mono text-lg text-red-500" {
-                                            (format!("-{} {:.2}", self.currency, amount.abs()))
+                                            (amount)
                                         }
-                                    } @else if *amount > 0.0 {
+                                    } @else if amount.is_positive() {
                                         span class="font-mono text-lg text-green-400" {
-                                            (format!("{} {:.2}", self.currency, amount))
+                                            (amount)
                                         }
                                     } @else {
                                         span class="font-mono text-lg text-gray-600" {
-                                            (format!("{} 0.00", self.currency))
+                                            (amount)
                                         }
                                     }
                                 }
                             }
                         }
                     }
+                    @if let Some(consumption) = &self.own_consumption {
+                        span class="font-mono text-xs text-gray-500 uppercase block mt-3" {
+                            "Your share: " (consumption)
+                        }
+                    }
                 }
             }
         }.into_string()
@@ -317,11 +557,25 @@ pub struct ExpenseForm {
     expense_id: Option<String>,
     description: String,
     paid_by: String,
-    amount: f64,
-    currency: String,
+    payers: Vec<Payer>,
+    transfer_to: Option<String>,
+    is_refund: bool,
+    original_transaction_id: Option<String>,
+    refundable_transactions: Vec<(String, String)>, // (id, description) pairs
+    amount: Money,
     date: String,
     split_ratios: Vec<Split>,
+    line_items: Vec<LineItem>,
+    tax: Option<Money>,
+    tip: Option<Money>,
+    category: Option<String>,
+    categories: Vec<(String, String)>, // (key, display_name) pairs
+    tags: Vec<String>,
     participants: Vec<(String, String)>, // (id, display_name) pairs
+    history: Vec<(String, String, Vec<String>)>, // (revision_id, heading, changes) triples
+    added_by: Option<(String, bool)>,    // (name, known member) pair
+    last_edited_by: Option<(String, bool)>, // (name, known member) pair
+    read_only: bool,
 }
 
 impl ExpenseForm {
@@ -330,11 +584,25 @@ impl ExpenseForm {
             expense_id: None,
             description: String::new(),
             paid_by: String::new(),
-            amount: 0.0,
-            currency: String::from("USD"),
+            payers: Vec::new(),
+            transfer_to: None,
+            is_refund: false,
+            original_transaction_id: None,
+            refundable_transactions: Vec::new(),
+            amount: Money::zero("USD"),
             date: String::new(),
             split_ratios: Vec::new(),
+            line_items: Vec::new(),
+            tax: None,
+            tip: None,
+            category: None,
+            categories: Vec::new(),
+            tags: Vec::new(),
             participants: Vec::new(),
+            history: Vec::new(),
+            added_by: None,
+            last_edited_by: None,
+            read_only: false,
         }
     }
 
@@ -353,13 +621,34 @@ impl ExpenseForm {
         self
     }
 
-    pub fn amount(mut self, amount: f64) -> Self {
-        self.amount = amount;
+    pub fn payers(mut self, payers: Vec<Payer>) -> Self {
+        self.payers = payers;
+        self
+    }
+
+    /// Turn the form into a transfer from `paid_by` to the given entity id.
+    /// Transfers have no splits, line items or additional payers.
+    pub fn transfer_to(mut self, entity_id: impl Into<String>) -> Self {
+        self.transfer_to = Some(entity_id.into());
+        self
+    }
+
+    /// Turn the form into a refund or income received by `paid_by`, optionally
+    /// refunding the transaction with the given id.
+    pub fn refund(mut self, original_transaction_id: Option<String>) -> Self {
+        self.is_refund = true;
+        self.original_transaction_id = original_transaction_id;
         self
     }
 
-    pub fn currency(mut self, currency: impl Into<String>) -> Self {
-        self.currency = currency.into();
+    /// Transactions a refund can point to.
+    pub fn refundable_transactions(mut self, transactions: Vec<(String, String)>) -> Self {
+        self.refundable_transactions = transactions;
+        self
+    }
+
+    pub fn amount(mut self, amount: Money) -> Self {
+        self.amount = amount;
         self
     }
 
@@ -373,6 +662,62 @@ impl ExpenseForm {
         self
     }
 
+    pub fn line_items(mut self, line_items: Vec<LineItem>) -> Self {
+        self.line_items = line_items;
+        self
+    }
+
+    pub fn tax(mut self, tax: Option<Money>) -> Self {
+        self.tax = tax;
+        self
+    }
+
+    pub fn tip(mut self, tip: Option<Money>) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Key of the selected category
+    pub fn category(mut self, key: Option<String>) -> Self {
+        self.category = key;
+        self
+    }
+
+    /// The group's categories to choose from.
+    pub fn categories(mut self, categories: Vec<(String, String)>) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Revisions of the transaction, newest first, with one line per changed field
+    pub fn history(mut self, history: Vec<(String, String, Vec<String>)>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Who added the transaction; unknown authors are flagged
+    pub fn added_by(mut self, name: impl Into<String>, known: bool) -> Self {
+        self.added_by = Some((name.into(), known));
+        self
+    }
+
+    /// Who changed the transaction last; unknown authors are flagged
+    pub fn last_edited_by(mut self, name: impl Into<String>, known: bool) -> Self {
+        self.last_edited_by = Some((name.into(), known));
+        self
+    }
+
+    /// Show the transaction without a way to save it
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
     pub fn participants(mut self, participants: Vec<(String, String)>) -> Self {
         self.participants = participants;
         self
@@ -380,16 +725,20 @@ impl ExpenseForm {
 
     pub fn build(self) -> String {
         let is_edit = self.expense_id.is_some();
-        let form_title = if is_edit {
-            "Edit Expense"
-        } else {
-            "Add Expense"
-        };
-        let submit_label = if is_edit {
-            "Update Expense"
+        let is_transfer = self.transfer_to.is_some();
+        let (kind, kind_label) = if is_transfer {
+            ("transfer", "Transfer")
+        } else if self.is_refund {
+            ("refund", "Refund")
         } else {
-            "Create Expense"
+            ("expense", "Expense")
         };
+        let form_title = format!("{} {}", if is_edit { "Edit" } else { "Add" }, kind_label);
+        let submit_label = format!(
+            "{} {}",
+            if is_edit { "Update" } else { "Create" },
+            kind_label
+        );
 
         // Extract date only (without time) for the date input
         let date_only = self.date.split('T').next().unwrap_or(&self.date);
@@ -419,8 +768,24 @@ impl ExpenseForm {
                         }
                     }
 
+                    // Authors, mapped to group members by their commit email
+                    @for (label, author) in [("Added by", &self.added_by), ("Last edited by", &self.last_edited_by)] {
+                        @if let Some((name, known)) = author {
+                            div class="mb-2 font-mono text-xs text-zinc-500 uppercase" {
+                                (label) " " (name)
+                                @if !known {
+                                    span class="ml-2 px-2 py-0.5 bg-red-900 text-red-300 rounded" {
+                                        "Unknown Author"
+                                    }
+                                }
+                            }
+                        }
+                    }
+
                     // Form
                     form class="space-y-6" {
+                    input type="hidden" name="kind" value=(kind);
+
                     // Description field
                     div class="form-group" {
                         label class="block text-sm font-mono text-zinc-400 uppercase mb-2" for="description" {
@@ -445,8 +810,9 @@ impl ExpenseForm {
                                 type="number"
                                 name="amount"
                                 id="amount"
-                                value=(format!("{:.2}", self.amount))
-                                step="0.01"
+                                // Refunds are stored negative but entered as the amount received
+                                value=(self.amount.abs().format_amount())
+                                step=(self.amount.input_step())
                                 min="0"
                                 required
                                 class="w-full bg-zinc-800 border border-zinc-700 rounded px-4 py-3 text-white focus:border-orange-500 focus:outline-none transition-colors";
@@ -461,11 +827,11 @@ impl ExpenseForm {
                                 id="currency"
                                 required
                                 class="w-full bg-zinc-800 border border-zinc-700 rounded px-4 py-3 text-white focus:border-orange-500 focus:outline-none transition-colors" {
-                                option value="USD" selected[self.currency == "USD"] { "USD" }
-                                option value="EUR" selected[self.currency == "EUR"] { "EUR" }
-                                option value="GBP" selected[self.currency == "GBP"] { "GBP" }
-                                option value="CHF" selected[self.currency == "CHF"] { "CHF" }
-                                option value="JPY" selected[self.currency == "JPY"] { "JPY" }
+                                option value="USD" selected[self.amount.currency() == "USD"] { "USD" }
+                                option value="EUR" selected[self.amount.currency() == "EUR"] { "EUR" }
+                                option value="GBP" selected[self.amount.currency() == "GBP"] { "GBP" }
+                                option value="CHF" selected[self.amount.currency() == "CHF"] { "CHF" }
+                                option value="JPY" selected[self.amount.currency() == "JPY"] { "JPY" }
                             }
                         }
                     }
@@ -474,7 +840,13 @@ impl ExpenseForm {
                     div class="grid grid-cols-2 gap-4" {
                         div class="form-group" {
                             label class="block text-sm font-mono text-zinc-400 uppercase mb-2" for="paid_by" {
-                                "Paid By"
+                                (if is_transfer {
+                                    "From"
+                                } else if self.is_refund {
+                                    "Received By"
+                                } else {
+                                    "Paid By"
+                                })
                             }
                             select
                                 name="paid_by"
@@ -503,35 +875,168 @@ impl ExpenseForm {
                         }
                     }
 
-                    // Split ratios section
-                    div class="form-group" {
-                        label class="block text-sm font-mono text-zinc-400 uppercase mb-3" {
-                            "Split Between"
+                    // Category and tags
+                    div class="grid grid-cols-2 gap-4" {
+                        div class="form-group" {
+                            label class="block text-sm font-mono text-zinc-400 uppercase mb-2" for="category" {
+                                "Category"
+                            }
+                            select
+                                name="category"
+                                id="category"
+                                class="w-full bg-zinc-800 border border-zinc-700 rounded px-4 py-3 text-white focus:border-orange-500 focus:outline-none transition-colors" {
+                                option value="" selected[self.category.is_none()] { "None" }
+                                @for (key, name) in &self.categories {
+                                    option value=(key) selected[self.category.as_deref() == Some(key.as_str())] {
+                                        (name)
+                                    }
+                                }
+                            }
                         }
-                        div class="space-y-2" {
-                            @for (participant_id, participant_name) in &self.participants {
-                                @let split = self.split_ratios.iter().find(|s| s.entity_id.to_string() == *participant_id);
-                                @let ratio_value = split.map(|s| format!("{}/{}", s.ratio.numerator(), s.ratio.denominator())).unwrap_or_else(|| "0/1".to_string());
-                                @let is_included = split.is_some();
 
-                                div class="flex items-center gap-4 bg-zinc-800 border border-zinc-700 rounded px-4 py-3" {
-                                    input
-                                        type="checkbox"
-                                        name=(format!("split_include_{}", participant_id))
-                                        id=(format!("split_include_{}", participant_id))
-                                        checked[is_included]
-                                        class="w-4 h-4 accent-orange-500";
-
-                                    label for=(format!("split_include_{}", participant_id)) class="flex-1 text-white" {
-                                        (participant_name)
+                        div class="form-group" {
+                            label class="block text-sm font-mono text-zinc-400 uppercase mb-2" for="tags" {
+                                "Tags"
+                            }
+                            input
+                                type="text"
+                                name="tags"
+                                id="tags"
+                                value=(self.tags.join(", "))
+                                placeholder="comma, separated"
+                                class="w-full bg-zinc-800 border border-zinc-700 rounded px-4 py-3 text-white focus:border-orange-500 focus:outline-none transition-colors";
+                        }
+                    }
+
+                    // Recipient of a transfer
+                    @if let Some(transfer_to) = &self.transfer_to {
+                        div class="form-group" {
+                            label class="block text-sm font-mono text-zinc-400 uppercase mb-2" for="to_entity" {
+                                "To"
+                            }
+                            select
+                                name="to_entity"
+                                id="to_entity"
+                                required
+                                class="w-full bg-zinc-800 border border-zinc-700 rounded px-4 py-3 text-white focus:border-orange-500 focus:outline-none transition-colors" {
+                                @for (id, name) in &self.participants {
+                                    option value=(id) selected[id == transfer_to] {
+                                        (name)
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    // Transaction a refund belongs to, none for income
+                    @if self.is_refund {
+                        div class="form-group" {
+                            label class="block text-sm font-mono text-zinc-400 uppercase mb-2" for="original_transaction_id" {
+                                "Refund Of"
+                            }
+                            select
+                                name="original_transaction_id"
+                                id="original_transaction_id"
+                                class="w-full bg-zinc-800 border border-zinc-700 rounded px-4 py-3 text-white focus:border-orange-500 focus:outline-none transition-colors" {
+                                option value="" selected[self.original_transaction_id.is_none()] {
+                                    "Nothing (income)"
+                                }
+                                @for (id, description) in &self.refundable_transactions {
+                                    option value=(id) selected[self.original_transaction_id.as_ref() == Some(id)] {
+                                        (description)
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    @if !is_transfer {
+                        // Multiple payers section
+                        details class="form-group" open[!self.payers.is_empty()] {
+                            summary class="text-sm font-mono text-zinc-400 uppercase mb-3 cursor-pointer" {
+                                "Paid By Several People"
+                            }
+                            div class="space-y-2" {
+                                @for (participant_id, participant_name) in &self.participants {
+                                    @let paid = self.payers.iter().find(|p| p.entity_id.to_string() == *participant_id);
+
+                                    div class="flex items-center gap-4 bg-zinc-800 border border-zinc-700 rounded px-4 py-3" {
+                                        label for=(format!("payer_amount_{}", participant_id)) class="flex-1 text-white" {
+                                            (participant_name)
+                                        }
+
+                                        input
+                                            type="number"
+                                            name=(format!("payer_amount_{}", participant_id))
+                                            id=(format!("payer_amount_{}", participant_id))
+                                            value=(paid.map(|p| p.amount.format_amount()).unwrap_or_default())
+                                            step=(self.amount.input_step())
+                                            min="0"
+                                            class="w-32 bg-zinc-900 border border-zinc-600 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
                                     }
+                                }
+                            }
+                        }
+
+                        // Split ratios section
+                        (PreEscaped(
+                            SplitSection::new()
+                                .splits(self.split_ratios.clone())
+                                .participants(self.participants.clone())
+                                .build()
+                        ))
+
+                        // Line items section
+                        div class="form-group" {
+                            label class="block text-sm font-mono text-zinc-400 uppercase mb-3" {
+                                "Line Items"
+                            }
+                            div id="line-items" class="space-y-3" {
+                                @for (index, item) in self.line_items.iter().enumerate() {
+                                    (PreEscaped(
+                                        LineItemRow::new()
+                                            .key(index.to_string())
+                                            .description(&item.description)
+                                            .amount(item.amount.clone())
+                                            .splits(item.splits.clone())
+                                            .participants(self.participants.clone())
+                                            .build()
+                                    ))
+                                }
+                            }
+                            button
+                                type="button"
+                                class="mt-3 text-sm font-mono text-zinc-400 hover:text-orange-500 uppercase transition-colors"
+                                hx-tauri-invoke="new_line_item"
+                                hx-target="#line-items"
+                                hx-swap="beforeend" {
+                                "+ Add Line Item"
+                            }
 
+                            div class="grid grid-cols-2 gap-4 mt-4" {
+                                div {
+                                    label class="block text-sm font-mono text-zinc-400 uppercase mb-2" for="tax" {
+                                        "Tax"
+                                    }
+                                    input
+                                        type="number"
+                                        name="tax"
+                                        id="tax"
+                                        value=(self.tax.as_ref().map(Money::format_amount).unwrap_or_default())
+                                        step=(self.amount.input_step())
+                                        class="w-full bg-zinc-800 border border-zinc-700 rounded px-4 py-3 text-white focus:border-orange-500 focus:outline-none transition-colors";
+                                }
+                                div {
+                                    label class="block text-sm font-mono text-zinc-400 uppercase mb-2" for="tip" {
+                                        "Tip"
+                                    }
                                     input
-                                        type="text"
-                                        name=(format!("split_ratio_{}", participant_id))
-                                        placeholder="1/1"
-                                        value=(ratio_value)
-                                        class="w-24 bg-zinc-900 border border-zinc-600 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
+                                        type="number"
+                                        name="tip"
+                                        id="tip"
+                                        value=(self.tip.as_ref().map(Money::format_amount).unwrap_or_default())
+                                        step=(self.amount.input_step())
+                                        class="w-full bg-zinc-800 border border-zinc-700 rounded px-4 py-3 text-white focus:border-orange-500 focus:outline-none transition-colors";
                                 }
                             }
                         }
@@ -547,15 +1052,75 @@ impl ExpenseForm {
                             "Cancel"
                         }
 
-                        button
-                            type="submit"
-                            class="flex-1 bg-orange-500 hover:bg-orange-600 text-white font-semibold py-3 px-6 rounded transition-colors"
-                            hx-tauri-invoke=(if is_edit { "update_expense" } else { "create_expense" })
-                            hx-target="#main-content" {
-                            (submit_label)
+                        @if !self.read_only {
+                            button
+                                type="submit"
+                                class="flex-1 bg-orange-500 hover:bg-orange-600 text-white font-semibold py-3 px-6 rounded transition-colors"
+                                hx-tauri-invoke=(if is_edit { "update_expense" } else { "create_expense" })
+                                hx-target="#main-content" {
+                                (submit_label)
+                            }
                         }
                     }
                 }
+
+                    // Who changed what and when, newest first
+                    @if !self.history.is_empty() {
+                        details class="mt-8 border-t border-zinc-700 pt-4" {
+                            summary class="text-sm font-mono text-zinc-400 uppercase cursor-pointer" {
+                                "History (" (self.history.len()) ")"
+                            }
+                            ol class="mt-4 space-y-4" {
+                                @for (revision_id, heading, changes) in &self.history {
+                                    li class="border-l border-zinc-700 pl-4" {
+                                        // The form sends the transaction id along with the revision
+                                        form class="flex items-center gap-4" {
+                                            input type="hidden" name="expenseId" value=(self.expense_id.as_deref().unwrap_or_default());
+                                            span class="font-mono text-xs text-zinc-500 uppercase" {
+                                                (heading)
+                                            }
+                                            button
+                                                type="button"
+                                                name="revision"
+                                                value=(revision_id)
+                                                class="font-mono text-xs text-zinc-500 uppercase hover:text-orange-500 transition-colors"
+                                                hx-tauri-invoke="time_travel"
+                                                hx-target="#main-content" {
+                                                "View Ledger Here"
+                                            }
+                                            @if !self.read_only {
+                                                button
+                                                    type="button"
+                                                    name="revision"
+                                                    value=(revision_id)
+                                                    class="font-mono text-xs text-zinc-500 uppercase hover:text-orange-500 transition-colors"
+                                                    hx-tauri-invoke="restore_transaction"
+                                                    hx-target="#expense-list"
+                                                    hx-swap="outerHTML" {
+                                                    "Restore This Version"
+                                                }
+                                                button
+                                                    type="button"
+                                                    name="revision"
+                                                    value=(revision_id)
+                                                    class="font-mono text-xs text-zinc-500 uppercase hover:text-red-500 transition-colors"
+                                                    hx-tauri-invoke="revert_revision"
+                                                    hx-target="#expense-list"
+                                                    hx-swap="outerHTML" {
+                                                    "Undo This Change"
+                                                }
+                                            }
+                                        }
+                                        @for change in changes {
+                                            span class="block font-mono text-sm text-zinc-300" {
+                                                (change)
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
             }
         }
         }.into_string()
@@ -567,3 +1132,1024 @@ impl Default for ExpenseForm {
         Self::new()
     }
 }
+
+/// One editable line item of an itemized expense.
+///
+/// Rendered inside `ExpenseForm` and on its own when a line item is added.
+/// Form fields are prefixed with `line_item_<key>_` so rows can be told apart.
+/// The "Split Between" part of the expense form.
+///
+/// Rendered on its own when the splits are recomputed, e.g. per night.
+pub struct SplitSection {
+    splits: Vec<Split>,
+    participants: Vec<(String, String)>, // (id, display_name) pairs
+}
+
+impl SplitSection {
+    pub fn new() -> Self {
+        Self {
+            splits: Vec::new(),
+            participants: Vec::new(),
+        }
+    }
+
+    pub fn splits(mut self, splits: Vec<Split>) -> Self {
+        self.splits = splits;
+        self
+    }
+
+    pub fn participants(mut self, participants: Vec<(String, String)>) -> Self {
+        self.participants = participants;
+        self
+    }
+
+    pub fn build(self) -> String {
+        // All splits of a transaction share one mode
+        let split_mode = self
+            .splits
+            .first()
+            .map(|s| s.share.mode())
+            .unwrap_or(SplitMode::Ratio);
+
+        html! {
+            div class="form-group" id="split-section" {
+                div class="flex items-center justify-between mb-3" {
+                    label class="block text-sm font-mono text-zinc-400 uppercase" for="split_mode" {
+                        "Split Between"
+                    }
+                    select
+                        name="split_mode"
+                        id="split_mode"
+                        class="bg-zinc-800 border border-zinc-700 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none transition-colors" {
+                        @for mode in SplitMode::ALL {
+                            option value=(mode.key()) selected[mode == split_mode] {
+                                (mode.label())
+                            }
+                        }
+                    }
+                }
+                div class="space-y-2" {
+                    @for (participant_id, participant_name) in &self.participants {
+                        @let split = self.splits.iter().find(|s| s.entity_id.to_string() == *participant_id);
+                        @let split_value = split.map(|s| s.share.input_value()).unwrap_or_default();
+                        @let is_included = split.is_some();
+
+                        div class="flex items-center gap-4 bg-zinc-800 border border-zinc-700 rounded px-4 py-3" {
+                            input
+                                type="checkbox"
+                                name=(format!("split_include_{}", participant_id))
+                                id=(format!("split_include_{}", participant_id))
+                                checked[is_included]
+                                class="w-4 h-4 accent-orange-500";
+
+                            label for=(format!("split_include_{}", participant_id)) class="flex-1 text-white" {
+                                (participant_name)
+                            }
+
+                            input
+                                type="text"
+                                name=(format!("split_value_{}", participant_id))
+                                placeholder=(split_mode.placeholder())
+                                value=(split_value)
+                                class="w-24 bg-zinc-900 border border-zinc-600 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
+                        }
+                    }
+                }
+
+                // Accommodation split by nights stayed
+                details class="mt-3" {
+                    summary class="text-xs font-mono text-zinc-500 uppercase cursor-pointer" {
+                        "Split Per Night"
+                    }
+                    div class="flex items-center gap-4 mt-2" {
+                        input
+                            type="date"
+                            name="checkIn"
+                            aria-label="Check-in"
+                            class="bg-zinc-800 border border-zinc-700 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
+                        input
+                            type="date"
+                            name="checkOut"
+                            aria-label="Check-out"
+                            class="bg-zinc-800 border border-zinc-700 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
+                        button
+                            type="button"
+                            class="text-sm font-mono text-zinc-400 hover:text-orange-500 uppercase transition-colors"
+                            hx-tauri-invoke="per_night_splits"
+                            hx-target="#split-section"
+                            hx-swap="outerHTML" {
+                            "Apply"
+                        }
+                    }
+                }
+            }
+        }.into_string()
+    }
+}
+
+impl Default for SplitSection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Category and tag filter shown above the transaction list.
+pub struct TransactionFilter {
+    categories: Vec<(String, String)>, // (key, display_name) pairs
+    category: Option<String>,
+    tag: Option<String>,
+}
+
+impl TransactionFilter {
+    pub fn new() -> Self {
+        Self {
+            categories: Vec::new(),
+            category: None,
+            tag: None,
+        }
+    }
+
+    pub fn categories(mut self, categories: Vec<(String, String)>) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    pub fn category(mut self, key: Option<String>) -> Self {
+        self.category = key;
+        self
+    }
+
+    pub fn tag(mut self, tag: Option<String>) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    pub fn build(self) -> String {
+        html! {
+            form
+                class="flex items-center gap-4 px-12 py-3 border-b border-zinc-700"
+                hx-tauri-invoke="render_transactions"
+                hx-trigger="change"
+                hx-target="#expense-list"
+                hx-swap="outerHTML" {
+                // Named apart from the expense form's fields, whose values
+                // reach render_transactions through its Cancel button
+                select
+                    name="filterCategory"
+                    aria-label="Category"
+                    class="bg-zinc-800 border border-zinc-700 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none" {
+                    option value="" selected[self.category.is_none()] { "All Categories" }
+                    @for (key, name) in &self.categories {
+                        option value=(key) selected[self.category.as_deref() == Some(key.as_str())] {
+                            (name)
+                        }
+                    }
+                }
+                input
+                    type="text"
+                    name="filterTag"
+                    placeholder="Tag"
+                    value=(self.tag.unwrap_or_default())
+                    class="bg-zinc-800 border border-zinc-700 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
+            }
+        }
+        .into_string()
+    }
+}
+
+impl Default for TransactionFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Balances and settlements across all ledgers of the group.
+pub struct GroupOverview {
+    ledger_count: usize,
+    currencies: Vec<CurrencyOverview>,
+}
+
+struct CurrencyOverview {
+    currency: String,
+    balances: Vec<(String, Money)>, // (entity_name, net balance) pairs
+    settlements: Vec<(String, String, Money)>, // (from_name, to_name, amount) triples
+    transfers: Vec<(String, String, String, Money)>, // (ledger_name, from_name, to_name, amount)
+}
+
+impl GroupOverview {
+    pub fn new() -> Self {
+        Self {
+            ledger_count: 0,
+            currencies: Vec::new(),
+        }
+    }
+
+    pub fn ledger_count(mut self, count: usize) -> Self {
+        self.ledger_count = count;
+        self
+    }
+
+    /// Add the net balances and settlements of one currency, with the
+    /// per-ledger transfers that recording the settlement writes
+    pub fn currency(
+        mut self,
+        currency: impl Into<String>,
+        balances: Vec<(String, Money)>,
+        settlements: Vec<(String, String, Money)>,
+        transfers: Vec<(String, String, String, Money)>,
+    ) -> Self {
+        self.currencies.push(CurrencyOverview {
+            currency: currency.into(),
+            balances,
+            settlements,
+            transfers,
+        });
+        self
+    }
+
+    pub fn build(self) -> String {
+        html! {
+            section id="expense-list" class="flex flex-col px-12 py-6 space-y-8" {
+                div class="flex items-center justify-between" {
+                    h2 class="text-2xl font-light uppercase tracking-tight" {
+                        "All Ledgers"
+                    }
+                    span class="font-mono text-xs text-gray-500 uppercase" {
+                        (self.ledger_count) " ledgers"
+                    }
+                }
+
+                @if self.currencies.is_empty() {
+                    span class="font-mono text-xl text-gray-600" {
+                        "All settled up"
+                    }
+                }
+
+                @for overview in &self.currencies {
+                    div class="space-y-4" {
+                        span class="font-mono text-xs text-orange-500 uppercase" {
+                            (overview.currency)
+                        }
+                        div class="space-y-2" {
+                            @for (name, balance) in &overview.balances {
+                                div class="flex justify-between border-b border-zinc-800 py-2" {
+                                    span class="text-zinc-300" { (name) }
+                                    @if balance.is_negative() {
+                                        span class="font-mono text-red-500" { (balance) }
+                                    } @else {
+                                        span class="font-mono text-green-400" { (balance) }
+                                    }
+                                }
+                            }
+                        }
+                        @if !overview.settlements.is_empty() {
+                            div class="space-y-1" {
+                                @for (from, to, amount) in &overview.settlements {
+                                    div class="font-mono text-sm text-zinc-400" {
+                                        (from) " → " (to) " • " (amount)
+                                    }
+                                }
+                            }
+                            div class="space-y-1" {
+                                span class="font-mono text-xs text-gray-500 uppercase" {
+                                    "Recorded per ledger"
+                                }
+                                @for (ledger, from, to, amount) in &overview.transfers {
+                                    div class="font-mono text-xs text-zinc-500" {
+                                        (ledger) ": " (from) " → " (to) " • " (amount)
+                                    }
+                                }
+                            }
+                            button
+                                type="button"
+                                name="currency"
+                                value=(overview.currency)
+                                class="text-sm font-mono text-zinc-400 hover:text-orange-500 uppercase transition-colors"
+                                hx-tauri-invoke="record_group_settlement"
+                                hx-target="#expense-list"
+                                hx-swap="outerHTML" {
+                                "Record As Paid"
+                            }
+                        }
+                    }
+                }
+            }
+        }.into_string()
+    }
+}
+
+impl Default for GroupOverview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Spending statistics of the current ledger over a date range.
+pub struct Statistics {
+    from: String,
+    to: String,
+    monthly: bool,
+    total: Option<Money>,
+    people: Vec<(String, Money, Money)>, // (name, paid, consumed) triples
+    categories: Vec<(String, Money)>,    // (category_name, amount) pairs
+    periods: Vec<(String, Money)>,       // (period_label, amount) pairs
+    largest: Vec<(String, String, Money)>, // (expense_id, description, amount) triples
+    charts: Vec<String>,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Self {
+            from: String::new(),
+            to: String::new(),
+            monthly: false,
+            total: None,
+            people: Vec::new(),
+            categories: Vec::new(),
+            periods: Vec::new(),
+            largest: Vec::new(),
+            charts: Vec::new(),
+        }
+    }
+
+    /// The date range, as `YYYY-MM-DD` or empty for an open end
+    pub fn range(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.from = from.into();
+        self.to = to.into();
+        self
+    }
+
+    /// Group spending over time per month instead of per day
+    pub fn monthly(mut self, monthly: bool) -> Self {
+        self.monthly = monthly;
+        self
+    }
+
+    pub fn total(mut self, total: Money) -> Self {
+        self.total = Some(total);
+        self
+    }
+
+    pub fn people(mut self, people: Vec<(String, Money, Money)>) -> Self {
+        self.people = people;
+        self
+    }
+
+    pub fn categories(mut self, categories: Vec<(String, Money)>) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    pub fn periods(mut self, periods: Vec<(String, Money)>) -> Self {
+        self.periods = periods;
+        self
+    }
+
+    pub fn largest(mut self, largest: Vec<(String, String, Money)>) -> Self {
+        self.largest = largest;
+        self
+    }
+
+    /// Add a rendered chart, shown above the tables
+    pub fn chart(mut self, chart: String) -> Self {
+        self.charts.push(chart);
+        self
+    }
+
+    pub fn build(self) -> String {
+        html! {
+            section id="expense-list" class="flex flex-col px-12 py-6 space-y-8" {
+                div class="flex items-center justify-between" {
+                    h2 class="text-2xl font-light uppercase tracking-tight" {
+                        "Statistics"
+                    }
+                    @if let Some(total) = &self.total {
+                        span class="font-mono text-lg text-orange-500" { (total) }
+                    }
+                }
+
+                form
+                    class="flex items-center gap-4"
+                    hx-tauri-invoke="render_statistics"
+                    hx-trigger="change"
+                    hx-target="#expense-list"
+                    hx-swap="outerHTML" {
+                    input
+                        type="date"
+                        name="from"
+                        aria-label="From"
+                        value=(self.from)
+                        class="bg-zinc-800 border border-zinc-700 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
+                    input
+                        type="date"
+                        name="to"
+                        aria-label="To"
+                        value=(self.to)
+                        class="bg-zinc-800 border border-zinc-700 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
+                    select
+                        name="period"
+                        aria-label="Period"
+                        class="bg-zinc-800 border border-zinc-700 rounded px-3 py-1 text-white text-sm focus:border-orange-500 focus:outline-none" {
+                        option value="day" selected[!self.monthly] { "Per Day" }
+                        option value="month" selected[self.monthly] { "Per Month" }
+                    }
+                }
+
+                @if self.total.is_none() {
+                    span class="font-mono text-xl text-gray-600" {
+                        "No expenses in this range"
+                    }
+                } @else {
+                    @if !self.charts.is_empty() {
+                        div class="grid grid-cols-1 md:grid-cols-2 gap-8" {
+                            @for chart in &self.charts {
+                                (PreEscaped(chart))
+                            }
+                        }
+                    }
+
+                    div class="space-y-2" {
+                        div class="grid grid-cols-3 font-mono text-xs text-gray-500 uppercase" {
+                            span { "Person" }
+                            span class="text-right" { "Paid" }
+                            span class="text-right" { "Consumed" }
+                        }
+                        @for (name, paid, consumed) in &self.people {
+                            div class="grid grid-cols-3 border-b border-zinc-800 py-2" {
+                                span class="text-zinc-300" { (name) }
+                                span class="font-mono text-right" { (paid) }
+                                span class="font-mono text-right" { (consumed) }
+                            }
+                        }
+                    }
+
+                    div class="grid grid-cols-2 gap-8" {
+                        div class="space-y-2" {
+                            span class="font-mono text-xs text-gray-500 uppercase" { "Per Category" }
+                            @for (name, amount) in &self.categories {
+                                div class="flex justify-between border-b border-zinc-800 py-1" {
+                                    span class="text-zinc-300" { (name) }
+                                    span class="font-mono" { (amount) }
+                                }
+                            }
+                        }
+                        div class="space-y-2" {
+                            span class="font-mono text-xs text-gray-500 uppercase" {
+                                (if self.monthly { "Per Month" } else { "Per Day" })
+                            }
+                            @for (label, amount) in &self.periods {
+                                div class="flex justify-between border-b border-zinc-800 py-1" {
+                                    span class="font-mono text-zinc-300" { (label) }
+                                    span class="font-mono" { (amount) }
+                                }
+                            }
+                        }
+                    }
+
+                    div class="space-y-2" {
+                        span class="font-mono text-xs text-gray-500 uppercase" { "Largest Expenses" }
+                        @for (expense_id, description, amount) in &self.largest {
+                            button
+                                type="button"
+                                name="expenseId"
+                                value=(expense_id)
+                                class="flex justify-between w-full border-b border-zinc-800 py-1 text-left hover:text-orange-500 transition-colors"
+                                hx-tauri-invoke="get_expense"
+                                hx-target="#expense-list" {
+                                span { (description) }
+                                span class="font-mono" { (amount) }
+                            }
+                        }
+                    }
+                }
+            }
+        }.into_string()
+    }
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What was added, edited and deleted since the user last looked.
+pub struct ChangeSummary {
+    since: String,
+    added: Vec<(String, Money)>,   // (description, amount) pairs
+    edited: Vec<(String, Money)>,  // (description, amount) pairs
+    deleted: Vec<(String, Money)>, // (description, amount) pairs
+    balance_impact: Option<Money>,
+}
+
+impl ChangeSummary {
+    pub fn new() -> Self {
+        Self {
+            since: String::new(),
+            added: Vec::new(),
+            edited: Vec::new(),
+            deleted: Vec::new(),
+            balance_impact: None,
+        }
+    }
+
+    /// Description of the revision the user last looked at
+    pub fn since(mut self, label: impl Into<String>) -> Self {
+        self.since = label.into();
+        self
+    }
+
+    pub fn added(mut self, added: Vec<(String, Money)>) -> Self {
+        self.added = added;
+        self
+    }
+
+    pub fn edited(mut self, edited: Vec<(String, Money)>) -> Self {
+        self.edited = edited;
+        self
+    }
+
+    pub fn deleted(mut self, deleted: Vec<(String, Money)>) -> Self {
+        self.deleted = deleted;
+        self
+    }
+
+    /// How much the changes moved the user's balance
+    pub fn balance_impact(mut self, amount: Money) -> Self {
+        self.balance_impact = Some(amount);
+        self
+    }
+
+    pub fn build(self) -> String {
+        let sections = [
+            ("Added", &self.added),
+            ("Edited", &self.edited),
+            ("Deleted", &self.deleted),
+        ];
+        let is_empty = sections.iter().all(|(_, entries)| entries.is_empty());
+
+        html! {
+            section id="expense-list" class="flex flex-col px-12 py-6 space-y-8" {
+                div class="flex items-center justify-between" {
+                    h2 class="text-2xl font-light uppercase tracking-tight" {
+                        "What Changed"
+                    }
+                    span class="font-mono text-xs text-gray-500 uppercase" {
+                        "Since " (self.since)
+                    }
+                }
+
+                @if is_empty {
+                    span class="font-mono text-xl text-gray-600" {
+                        "Nothing new"
+                    }
+                }
+
+                @for (title, entries) in sections {
+                    @if !entries.is_empty() {
+                        div class="space-y-2" {
+                            span class="font-mono text-xs text-orange-500 uppercase" {
+                                (title) " (" (entries.len()) ")"
+                            }
+                            @for (description, amount) in entries {
+                                div class="flex justify-between border-b border-zinc-800 py-2" {
+                                    span class="text-zinc-300" { (description) }
+                                    span class="font-mono text-zinc-400" { (amount) }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                @if let Some(impact) = &self.balance_impact {
+                    div class="flex justify-between font-mono" {
+                        span class="text-xs text-gray-500 uppercase" { "Your balance changed by" }
+                        @if impact.is_negative() {
+                            span class="text-lg text-red-500" { (impact) }
+                        } @else {
+                            span class="text-lg text-green-400" { (impact) }
+                        }
+                    }
+                }
+
+                button
+                    type="button"
+                    class="self-start text-sm font-mono text-zinc-400 hover:text-orange-500 uppercase transition-colors"
+                    hx-tauri-invoke="mark_seen"
+                    hx-target="#expense-list"
+                    hx-swap="outerHTML" {
+                    "Mark As Seen"
+                }
+            }
+        }
+        .into_string()
+    }
+}
+
+impl Default for ChangeSummary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Colors of chart series and slices, in order.
+const CHART_COLORS: [&str; 6] = [
+    "#f97316", "#38bdf8", "#34d399", "#f472b6", "#facc15", "#a78bfa",
+];
+
+fn chart_color(index: usize) -> &'static str {
+    CHART_COLORS[index % CHART_COLORS.len()]
+}
+
+/// Line chart of amounts over time, rendered as inline SVG.
+pub struct LineChart {
+    title: String,
+    labels: Vec<String>,               // x axis label per point
+    series: Vec<(String, Vec<Money>)>, // (name, amount per point) pairs
+}
+
+impl LineChart {
+    const WIDTH: f64 = 600.0;
+    const HEIGHT: f64 = 200.0;
+    const PADDING: f64 = 8.0;
+
+    pub fn new() -> Self {
+        Self {
+            title: String::new(),
+            labels: Vec::new(),
+            series: Vec::new(),
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn labels(mut self, labels: Vec<String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Add a line, with one amount per label
+    pub fn series(mut self, name: impl Into<String>, points: Vec<Money>) -> Self {
+        self.series.push((name.into(), points));
+        self
+    }
+
+    pub fn build(self) -> String {
+        let values = self
+            .series
+            .iter()
+            .flat_map(|(_, points)| points.iter().map(|p| p.minor_units()));
+        // Always include zero so the axis shows who is owed and who owes
+        let min = values.clone().min().unwrap_or(0).min(0) as f64;
+        let max = (values.max().unwrap_or(0).max(0) as f64).max(min + 1.0);
+        let count = self.labels.len().max(1);
+
+        let x = |index: usize| {
+            if count == 1 {
+                Self::WIDTH / 2.0
+            } else {
+                Self::PADDING
+                    + index as f64 * (Self::WIDTH - 2.0 * Self::PADDING) / (count - 1) as f64
+            }
+        };
+        let y = |units: f64| {
+            Self::PADDING + (max - units) / (max - min) * (Self::HEIGHT - 2.0 * Self::PADDING)
+        };
+        let points = |amounts: &[Money]| {
+            amounts
+                .iter()
+                .enumerate()
+                .map(|(i, amount)| format!("{:.1},{:.1}", x(i), y(amount.minor_units() as f64)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        html! {
+            figure class="space-y-2" {
+                figcaption class="font-mono text-xs text-gray-500 uppercase" { (self.title) }
+                svg
+                    viewBox=(format!("0 0 {} {}", Self::WIDTH, Self::HEIGHT))
+                    class="w-full h-auto"
+                    role="img"
+                    aria-label=(self.title) {
+                    line
+                        x1="0"
+                        x2=(Self::WIDTH)
+                        y1=(format!("{:.1}", y(0.0)))
+                        y2=(format!("{:.1}", y(0.0)))
+                        stroke="#3f3f46"
+                        stroke-width="1" {}
+                    @for (index, (_, amounts)) in self.series.iter().enumerate() {
+                        polyline
+                            points=(points(amounts))
+                            fill="none"
+                            stroke=(chart_color(index))
+                            stroke-width="2"
+                            stroke-linejoin="round" {}
+                    }
+                }
+                div class="flex justify-between font-mono text-[0.65rem] text-gray-500" {
+                    span { (self.labels.first().cloned().unwrap_or_default()) }
+                    span { (self.labels.last().cloned().unwrap_or_default()) }
+                }
+                div class="flex flex-wrap gap-4 font-mono text-xs text-zinc-400" {
+                    @for (index, (name, amounts)) in self.series.iter().enumerate() {
+                        span class="flex items-center gap-1" {
+                            span class="inline-block w-3 h-3 rounded-sm" style=(format!("background: {}", chart_color(index))) {}
+                            (name)
+                            @if let Some(last) = amounts.last() {
+                                " " (last)
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        .into_string()
+    }
+}
+
+impl Default for LineChart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Donut chart of how an amount is made up, rendered as inline SVG.
+///
+/// Slices are drawn as dashes on a circle with a circumference of 100, so a
+/// slice's dash length is its percentage.
+pub struct DonutChart {
+    title: String,
+    slices: Vec<(String, Money)>, // (label, amount) pairs
+}
+
+impl DonutChart {
+    /// Radius of a circle with a circumference of 100
+    const RADIUS: f64 = 15.915_494;
+
+    pub fn new() -> Self {
+        Self {
+            title: String::new(),
+            slices: Vec::new(),
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Slices; only positive amounts are drawn
+    pub fn slices(mut self, slices: Vec<(String, Money)>) -> Self {
+        self.slices = slices;
+        self
+    }
+
+    pub fn build(self) -> String {
+        let slices: Vec<(String, Money)> = self
+            .slices
+            .into_iter()
+            .filter(|(_, amount)| amount.is_positive())
+            .collect();
+        let total: i64 = slices.iter().map(|(_, amount)| amount.minor_units()).sum();
+        let mut start = 0.0;
+        let arcs: Vec<(f64, f64)> = slices
+            .iter()
+            .map(|(_, amount)| {
+                let percent = amount.minor_units() as f64 * 100.0 / total.max(1) as f64;
+                let arc = (start, percent);
+                start += percent;
+                arc
+            })
+            .collect();
+
+        html! {
+            figure class="space-y-2" {
+                figcaption class="font-mono text-xs text-gray-500 uppercase" { (self.title) }
+                div class="flex items-center gap-6" {
+                    svg viewBox="0 0 42 42" class="w-40 h-40 flex-shrink-0" role="img" aria-label=(self.title) {
+                        circle cx="21" cy="21" r=(Self::RADIUS) fill="none" stroke="#27272a" stroke-width="6" {}
+                        @for (index, (start, percent)) in arcs.iter().enumerate() {
+                            // Dashes start at 3 o'clock; offset 25 moves them to 12 o'clock
+                            circle
+                                cx="21"
+                                cy="21"
+                                r=(Self::RADIUS)
+                                fill="none"
+                                stroke=(chart_color(index))
+                                stroke-width="6"
+                                stroke-dasharray=(format!("{:.3} {:.3}", percent, 100.0 - percent))
+                                stroke-dashoffset=(format!("{:.3}", 25.0 - start)) {}
+                        }
+                    }
+                    div class="space-y-1 font-mono text-xs text-zinc-400" {
+                        @for (index, ((label, amount), (_, percent))) in slices.iter().zip(&arcs).enumerate() {
+                            div class="flex items-center gap-2" {
+                                span class="inline-block w-3 h-3 rounded-sm" style=(format!("background: {}", chart_color(index))) {}
+                                (label) " " (amount) " (" (format!("{:.0}", percent)) "%)"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        .into_string()
+    }
+}
+
+impl Default for DonutChart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bar chart comparing what each person paid and consumed, as inline SVG.
+pub struct BarChart {
+    title: String,
+    rows: Vec<(String, Money, Money)>, // (name, paid, consumed) triples
+}
+
+impl BarChart {
+    const WIDTH: f64 = 600.0;
+    const LABEL_WIDTH: f64 = 120.0;
+    const ROW_HEIGHT: f64 = 36.0;
+
+    pub fn new() -> Self {
+        Self {
+            title: String::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn rows(mut self, rows: Vec<(String, Money, Money)>) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    pub fn build(self) -> String {
+        let max = self
+            .rows
+            .iter()
+            .flat_map(|(_, paid, consumed)| [paid.minor_units(), consumed.minor_units()])
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        // Negative amounts (e.g. more refunds received than paid) show no bar
+        let width = |amount: &Money| {
+            amount.minor_units().max(0) as f64 / max * (Self::WIDTH - Self::LABEL_WIDTH)
+        };
+        let height = Self::ROW_HEIGHT * self.rows.len().max(1) as f64;
+
+        html! {
+            figure class="space-y-2" {
+                figcaption class="font-mono text-xs text-gray-500 uppercase" { (self.title) }
+                svg
+                    viewBox=(format!("0 0 {} {}", Self::WIDTH, height))
+                    class="w-full h-auto"
+                    role="img"
+                    aria-label=(self.title) {
+                    @for (index, (name, paid, consumed)) in self.rows.iter().enumerate() {
+                        @let top = index as f64 * Self::ROW_HEIGHT;
+                        text x="0" y=(format!("{:.1}", top + 20.0)) fill="#d4d4d8" font-size="12" { (name) }
+                        rect
+                            x=(Self::LABEL_WIDTH)
+                            y=(format!("{:.1}", top + 4.0))
+                            width=(format!("{:.1}", width(paid)))
+                            height="12"
+                            fill=(chart_color(0)) {
+                            title { "Paid " (paid) }
+                        }
+                        rect
+                            x=(Self::LABEL_WIDTH)
+                            y=(format!("{:.1}", top + 18.0))
+                            width=(format!("{:.1}", width(consumed)))
+                            height="12"
+                            fill=(chart_color(1)) {
+                            title { "Consumed " (consumed) }
+                        }
+                    }
+                }
+                div class="flex gap-4 font-mono text-xs text-zinc-400" {
+                    span class="flex items-center gap-1" {
+                        span class="inline-block w-3 h-3 rounded-sm" style=(format!("background: {}", chart_color(0))) {}
+                        "Paid"
+                    }
+                    span class="flex items-center gap-1" {
+                        span class="inline-block w-3 h-3 rounded-sm" style=(format!("background: {}", chart_color(1))) {}
+                        "Consumed"
+                    }
+                }
+            }
+        }
+        .into_string()
+    }
+}
+
+impl Default for BarChart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct LineItemRow {
+    key: String,
+    description: String,
+    amount: Money,
+    splits: Vec<Split>,
+    participants: Vec<(String, String)>, // (id, display_name) pairs
+}
+
+impl LineItemRow {
+    pub fn new() -> Self {
+        Self {
+            key: String::from("0"),
+            description: String::new(),
+            amount: Money::zero("USD"),
+            splits: Vec::new(),
+            participants: Vec::new(),
+        }
+    }
+
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.key = key.into();
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn amount(mut self, amount: Money) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    pub fn splits(mut self, splits: Vec<Split>) -> Self {
+        self.splits = splits;
+        self
+    }
+
+    pub fn participants(mut self, participants: Vec<(String, String)>) -> Self {
+        self.participants = participants;
+        self
+    }
+
+    pub fn build(self) -> String {
+        let prefix = format!("line_item_{}", self.key);
+        let split_mode = self
+            .splits
+            .first()
+            .map(|s| s.share.mode())
+            .unwrap_or(SplitMode::Shares);
+
+        html! {
+            div class="bg-zinc-900 border border-zinc-700 rounded p-4 space-y-3" {
+                div class="grid grid-cols-[1fr_10rem] gap-3" {
+                    input
+                        type="text"
+                        name=(format!("{}_description", prefix))
+                        placeholder="Item"
+                        value=(self.description)
+                        class="bg-zinc-800 border border-zinc-700 rounded px-3 py-2 text-white text-sm focus:border-orange-500 focus:outline-none";
+                    input
+                        type="number"
+                        name=(format!("{}_amount", prefix))
+                        value=(self.amount.format_amount())
+                        step=(self.amount.input_step())
+                        class="bg-zinc-800 border border-zinc-700 rounded px-3 py-2 text-white text-sm focus:border-orange-500 focus:outline-none";
+                }
+                input type="hidden" name=(format!("{}_split_mode", prefix)) value=(split_mode.key());
+                div class="flex flex-wrap gap-3" {
+                    @for (participant_id, participant_name) in &self.participants {
+                        @let split = self.splits.iter().find(|s| s.entity_id.to_string() == *participant_id);
+                        label class="flex items-center gap-2 text-sm text-zinc-300" {
+                            (participant_name)
+                            input
+                                type="text"
+                                name=(format!("{}_split_{}", prefix, participant_id))
+                                placeholder=(split_mode.placeholder())
+                                value=(split.map(|s| s.share.input_value()).unwrap_or_default())
+                                class="w-16 bg-zinc-800 border border-zinc-600 rounded px-2 py-1 text-white text-sm focus:border-orange-500 focus:outline-none";
+                        }
+                    }
+                }
+            }
+        }.into_string()
+    }
+}
+
+impl Default for LineItemRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml::value::{Date, Datetime, Offset, Time};
+
+/// Today's date in UTC.
+pub fn today() -> Date {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    date_from_day_number(seconds.div_euclid(86_400))
+}
+
+/// 1970-01-01T00:00:00Z, for times that cannot be written as a datetime.
+pub const EPOCH: Datetime = Datetime {
+    date: Some(Date {
+        year: 1970,
+        month: 1,
+        day: 1,
+    }),
+    time: Some(Time {
+        hour: 0,
+        minute: 0,
+        second: 0,
+        nanosecond: 0,
+    }),
+    offset: Some(Offset::Z),
+};
+
+/// The current time in UTC, with second precision.
+pub fn now() -> Datetime {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    datetime_from_timestamp(seconds, 0).unwrap_or(EPOCH)
+}
+
+/// The datetime `seconds` after 1970-01-01T00:00:00Z, in the time zone
+/// `offset_minutes` east of UTC (like git's commit times).
+///
+/// `None` if it has no RFC 3339 form: a local year outside 0–9999 or an
+/// offset of 100 hours or more. Commit times come from other people's
+/// clocks, so callers have to expect both.
+pub fn datetime_from_timestamp(seconds: i64, offset_minutes: i32) -> Option<Datetime> {
+    let local = seconds.checked_add(offset_minutes as i64 * 60)?;
+    let days = local.div_euclid(86_400);
+    if days < day_number(date(0, 1, 1)) || days > day_number(date(9999, 12, 31)) {
+        return None;
+    }
+    let date = date_from_day_number(days);
+    let time = local.rem_euclid(86_400);
+    let offset = if offset_minutes == 0 {
+        String::from("Z")
+    } else {
+        format!(
+            "{}{:02}:{:02}",
+            if offset_minutes < 0 { '-' } else { '+' },
+            offset_minutes.abs() / 60,
+            offset_minutes.abs() % 60
+        )
+    };
+    format!(
+        "{}T{:02}:{:02}:{:02}{}",
+        date,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        offset
+    )
+    .parse()
+    .ok()
+}
+
+/// Parse a date as entered in an `<input type="date">` (`2025-12-27`).
+pub fn parse_date(input: &str) -> Option<Date> {
+    let datetime: Datetime = input.trim().parse().ok()?;
+    match (datetime.date, datetime.time) {
+        (Some(date), None) => Some(date),
+        _ => None,
+    }
+}
+
+/// Seconds since 1970-01-01T00:00:00Z, for ordering datetimes.
+///
+/// Local datetimes are taken as UTC and a missing date as 1970-01-01.
+pub fn timestamp(datetime: &Datetime) -> i64 {
+    let days = datetime.date.map(day_number).unwrap_or(0);
+    let seconds = datetime
+        .time
+        .map(|time| time.hour as i64 * 3600 + time.minute as i64 * 60 + time.second as i64)
+        .unwrap_or(0);
+    let offset_minutes = match datetime.offset {
+        Some(Offset::Custom { minutes }) => minutes as i64,
+        _ => 0,
+    };
+    days * 86_400 + seconds - offset_minutes * 60
+}
+
+pub fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 (proleptic Gregorian calendar).
+pub fn day_number(date: Date) -> i64 {
+    let year = date.year as i64 - if date.month <= 2 { 1 } else { 0 };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = date.month as i64;
+    let day_of_year =
+        (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + date.day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+pub fn date_from_day_number(days: i64) -> Date {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    Date {
+        year: year.clamp(0, u16::MAX as i64) as u16,
+        month: month as u8,
+        day: day as u8,
+    }
+}
+
+fn date(year: u16, month: u8, day: u8) -> Date {
+    Date { year, month, day }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_number_round_trips() {
+        for sut in [date(1970, 1, 1), date(2000, 2, 29), date(2025, 12, 31)] {
+            assert_eq!(date_from_day_number(day_number(sut)), sut);
+        }
+        assert_eq!(day_number(date(1970, 1, 1)), 0);
+        assert_eq!(
+            day_number(date(2000, 3, 1)) - day_number(date(2000, 2, 28)),
+            2
+        );
+    }
+
+    #[test]
+    fn test_timestamp_respects_offset() {
+        let utc: Datetime = "2025-12-27T12:00:00Z".parse().unwrap();
+        let berlin: Datetime = "2025-12-27T13:00:00+01:00".parse().unwrap();
+
+        assert_eq!(timestamp(&utc), timestamp(&berlin));
+        assert_eq!(timestamp(&"1970-01-02T00:00:00Z".parse().unwrap()), 86_400);
+        assert_eq!(
+            datetime_from_timestamp(timestamp(&berlin), 60)
+                .unwrap()
+                .to_string(),
+            "2025-12-27T13:00:00+01:00"
+        );
+    }
+
+    #[test]
+    fn test_datetime_from_timestamp_out_of_range() {
+        let year_10000 = day_number(date(10000, 1, 1)) * 86_400;
+
+        assert_eq!(datetime_from_timestamp(year_10000, 0), None);
+        assert_eq!(
+            datetime_from_timestamp(year_10000 - 1, 0)
+                .unwrap()
+                .to_string(),
+            "9999-12-31T23:59:59Z"
+        );
+        assert_eq!(datetime_from_timestamp(-62_167_219_201, 0), None);
+        assert_eq!(datetime_from_timestamp(0, 100 * 60), None);
+        assert_eq!(datetime_from_timestamp(i64::MAX, 60), None);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2025-12-27"), Some(date(2025, 12, 27)));
+        assert_eq!(parse_date("2025-12-27T12:00:00Z"), None);
+        assert_eq!(parse_date(""), None);
+    }
+}
@@ -1,13 +1,36 @@
+use crate::canonical;
+use crate::dates;
+use crate::migrations::{self, FileKind, SCHEMA_VERSION};
 use crate::structs;
-use crate::traits::{PersistenceError, PersistenceRepository};
-use git2::{ObjectType, Repository, Tree};
-use std::collections::HashMap;
+use crate::traits::{
+    FieldChange, PersistenceError, PersistenceRepository, PointInTime, Revision, SignatureStatus,
+    TransactionChange, TransactionRevision,
+};
+use git2::build::CheckoutBuilder;
+use git2::{
+    Commit, DiffFile, DiffOptions, ErrorCode, FileMode, ObjectType, Oid, Repository, Signature,
+    Sort, Tree, TreeWalkMode, TreeWalkResult,
+};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::env;
+use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
 use std::str;
 use std::sync::Mutex;
+use toml::value::Date;
 use uuid::Uuid;
 
+/// Signing keys of every entity, by entity id
+type SigningKeys = Vec<(Uuid, Vec<String>)>;
+
+/// Unverified changes of a ledger's transactions, see `unverified_transactions`
+type UnverifiedTransactions = HashMap<Uuid, (Revision, SignatureStatus)>;
+
+/// Unverified transactions by read commit and ledger id
+type UnverifiedByCommit = HashMap<(Oid, Uuid), UnverifiedTransactions>;
+
 /// Git-backed implementation of PersistenceRepository.
 ///
 /// The repository is opened during construction. A map from ledger UUID -> path in the repo
@@ -19,6 +42,20 @@ pub struct GitPersistence {
 
     /// The path under repo root where ledgers live (default "ledgers")
     ledgers_root: PathBuf,
+
+    /// Commit to read from instead of main, see `view_at`
+    pinned_commit: Mutex<Option<Oid>>,
+
+    /// Name and email of new commits, see `set_author`
+    author: Mutex<Option<(String, String)>>,
+
+    /// Verified signatures by commit, valid for the signing keys they were
+    /// checked against
+    signatures: Mutex<(SigningKeys, HashMap<Oid, SignatureStatus>)>,
+
+    /// Unverified transactions by read commit and ledger, valid for the
+    /// signing keys they were checked against like `signatures`
+    unverified: Mutex<(SigningKeys, UnverifiedByCommit)>,
 }
 
 impl GitPersistence {
@@ -40,17 +77,26 @@ impl GitPersistence {
             repo,
             ledger_map: Mutex::new(HashMap::new()),
             ledgers_root: PathBuf::from("ledgers"),
+            pinned_commit: Mutex::new(None),
+            author: Mutex::new(None),
+            signatures: Mutex::new((Vec::new(), HashMap::new())),
+            unverified: Mutex::new((Vec::new(), HashMap::new())),
         })
     }
 
-    /// Resolve refs/heads/main or HEAD and return the repository root tree.
-    fn get_root_tree<'repo>(&'repo self) -> Result<Tree<'repo>, PersistenceError> {
+    /// Resolve refs/heads/main or HEAD and return the reference name and its commit.
+    fn head_commit(&self) -> Result<(String, Commit<'_>), PersistenceError> {
         let reference = self
             .repo
             .find_reference("refs/heads/main")
             .or_else(|_| self.repo.head())
             .map_err(|e| PersistenceError::Git(format!("failed to find main or HEAD: {}", e)))?;
 
+        let name = reference
+            .name()
+            .ok_or_else(|| PersistenceError::Other("reference name is not valid UTF-8".into()))?
+            .to_string();
+
         let target_oid = reference.target().ok_or_else(|| {
             PersistenceError::Other("reference does not point to an object".into())
         })?;
@@ -60,6 +106,49 @@ impl GitPersistence {
             .find_commit(target_oid)
             .map_err(|e| PersistenceError::Git(format!("failed to find commit: {}", e)))?;
 
+        Ok((name, commit))
+    }
+
+    /// The commit data is read from: the pinned commit, or main (or HEAD).
+    fn read_commit(&self) -> Result<Commit<'_>, PersistenceError> {
+        let pinned = *self
+            .pinned_commit
+            .lock()
+            .map_err(|e| PersistenceError::Other(format!("{}", e)))?;
+        match pinned {
+            Some(oid) => Ok(self.repo.find_commit(oid)?),
+            None => Ok(self.head_commit()?.1),
+        }
+    }
+
+    /// The newest commit reachable from main (or HEAD) made on or before
+    /// `date`, in the time zone of the commit's own time. That is the date
+    /// its revision is shown with, and the local date of whoever made it.
+    fn commit_until(&self, date: Date) -> Result<Commit<'_>, PersistenceError> {
+        let (_, head) = self.head_commit()?;
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME)?;
+        revwalk.push(head.id())?;
+
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            let local = commit
+                .time()
+                .seconds()
+                .saturating_add(commit.time().offset_minutes() as i64 * 60);
+            if local.div_euclid(86_400) <= dates::day_number(date) {
+                return Ok(commit);
+            }
+        }
+        Err(PersistenceError::NotFound(
+            "no commit before the given date".into(),
+        ))
+    }
+
+    /// Resolve the commit data is read from and return its root tree.
+    fn get_root_tree<'repo>(&'repo self) -> Result<Tree<'repo>, PersistenceError> {
+        let commit = self.read_commit()?;
+
         let root_tree = commit
             .tree()
             .map_err(|e| PersistenceError::Git(format!("failed to get tree: {}", e)))?;
@@ -125,6 +214,403 @@ impl GitPersistence {
         }
     }
 
+    /// Text of the blob with the given id.
+    fn blob_text(&self, oid: Oid) -> Result<String, PersistenceError> {
+        let blob = self
+            .repo
+            .find_blob(oid)
+            .map_err(|e| PersistenceError::Git(format!("failed to read blob: {}", e)))?;
+        let text =
+            str::from_utf8(blob.content()).map_err(|e| PersistenceError::Utf8(format!("{}", e)))?;
+        Ok(text.to_string())
+    }
+
+    /// Schema version of the data in a commit, from its `group.toml`.
+    fn schema_version_in(&self, commit: &Commit<'_>) -> Result<u32, PersistenceError> {
+        let oid = self
+            .blob_id_in_commit(commit, Path::new("group.toml"))?
+            .ok_or_else(|| {
+                PersistenceError::NotFound(format!("group.toml not found in {}", short_id(commit)))
+            })?;
+        migrations::schema_version(&self.blob_text(oid)?)
+    }
+
+    /// A transaction blob written with schema version `version`, upgraded
+    /// and parsed like the transactions that are listed.
+    fn transaction_from_blob(
+        &self,
+        oid: Oid,
+        version: u32,
+    ) -> Result<structs::Transaction, PersistenceError> {
+        let text = self.upgraded(FileKind::Transaction, &self.blob_text(oid)?, version)?;
+        toml::from_str(&text).map_err(|e| PersistenceError::Toml(format!("{}", e)))
+    }
+
+    /// Id of the blob at `path_in_repo` in a commit, `None` if there is none.
+    fn blob_id_in_commit(
+        &self,
+        commit: &Commit<'_>,
+        path_in_repo: &Path,
+    ) -> Result<Option<Oid>, PersistenceError> {
+        Ok(commit
+            .tree()?
+            .get_path(path_in_repo)
+            .ok()
+            .filter(|entry| entry.kind() == Some(ObjectType::Blob))
+            .map(|entry| entry.id()))
+    }
+
+    /// Commits reachable from the read commit that changed the file at
+    /// `path_in_repo`, newest first, with the file's blob in the first parent
+    /// and in the commit.
+    ///
+    /// Like `git log <path>`, merges that took the file unchanged from one of
+    /// their parents are skipped.
+    fn file_revisions(
+        &self,
+        path_in_repo: &Path,
+    ) -> Result<Vec<(Commit<'_>, Option<Oid>, Option<Oid>)>, PersistenceError> {
+        let head = self.read_commit()?;
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        revwalk.push(head.id())?;
+
+        let mut revisions = Vec::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            let after = self.blob_id_in_commit(&commit, path_in_repo)?;
+            let before = commit
+                .parents()
+                .map(|parent| self.blob_id_in_commit(&parent, path_in_repo))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // A root commit changed the file if it contains it
+            let unchanged = if before.is_empty() {
+                after.is_none()
+            } else {
+                before.contains(&after)
+            };
+            if !unchanged {
+                revisions.push((commit, before.first().copied().flatten(), after));
+            }
+        }
+        Ok(revisions)
+    }
+
+    /// Transaction ids of a ledger whose files a commit changed.
+    ///
+    /// Like in `file_revisions`, merges only count for files that differ
+    /// from every parent.
+    fn changed_transactions(
+        &self,
+        commit: &Commit<'_>,
+        ledger_path: &Path,
+    ) -> Result<HashSet<Uuid>, PersistenceError> {
+        let tree = commit.tree()?;
+        let parents: Vec<Option<Tree<'_>>> = if commit.parent_count() == 0 {
+            vec![None]
+        } else {
+            commit
+                .parents()
+                .map(|parent| parent.tree().map(Some))
+                .collect::<Result<_, _>>()?
+        };
+
+        let mut changed: Option<HashSet<Uuid>> = None;
+        for parent in parents {
+            let mut options = DiffOptions::new();
+            options.pathspec(ledger_path);
+            let diff =
+                self.repo
+                    .diff_tree_to_tree(parent.as_ref(), Some(&tree), Some(&mut options))?;
+            let ids: HashSet<Uuid> = diff
+                .deltas()
+                .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+                .filter(|path| path.parent() == Some(ledger_path))
+                .filter_map(|path| path.file_stem()?.to_str()?.parse().ok())
+                .collect();
+            changed = Some(match changed {
+                Some(changed) => changed.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+        Ok(changed.unwrap_or_default())
+    }
+
+    /// Signature status of a commit, cached as long as the signing keys of
+    /// the group stay the same.
+    fn signature_status(
+        &self,
+        commit_id: Oid,
+        group: &structs::Group,
+    ) -> Result<SignatureStatus, PersistenceError> {
+        let keys = signing_keys(group);
+        let mut cache = self
+            .signatures
+            .lock()
+            .map_err(|e| PersistenceError::Other(format!("{}", e)))?;
+        if cache.0 != keys {
+            *cache = (keys, HashMap::new());
+        }
+        if let Some(status) = cache.1.get(&commit_id) {
+            return Ok(status.clone());
+        }
+
+        let status = match self.repo.extract_signature(&commit_id, None) {
+            // Without ssh-keygen or gpg this commit is unverifiable, the
+            // others can still be checked
+            Ok((signature, signed_data)) => verify_signature(&signature, &signed_data, group)
+                .unwrap_or_else(|e| {
+                    eprintln!("cannot verify the signature of {}: {}", commit_id, e);
+                    SignatureStatus::Unverifiable
+                }),
+            Err(e) if e.code() == ErrorCode::NotFound => SignatureStatus::Unsigned,
+            Err(e) => return Err(e.into()),
+        };
+        cache.1.insert(commit_id, status.clone());
+        Ok(status)
+    }
+
+    /// Path of a ledger folder (relative to repo root) from the ledger map.
+    fn ledger_path(&self, ledger_id: Uuid) -> Result<PathBuf, PersistenceError> {
+        let map = self
+            .ledger_map
+            .lock()
+            .map_err(|e| PersistenceError::Other(format!("{}", e)))?;
+        map.get(&ledger_id)
+            .cloned()
+            .ok_or_else(|| PersistenceError::NotFound(format!("ledger id {} not found", ledger_id)))
+    }
+
+    /// Path of a transaction file (relative to repo root).
+    fn transaction_path(
+        &self,
+        ledger_id: Uuid,
+        transaction_id: Uuid,
+    ) -> Result<PathBuf, PersistenceError> {
+        Ok(self
+            .ledger_path(ledger_id)?
+            .join(format!("{}.toml", transaction_id)))
+    }
+
+    fn path_exists(&self, path_in_repo: &Path) -> Result<bool, PersistenceError> {
+        Ok(self.get_root_tree()?.get_path(path_in_repo).is_ok())
+    }
+
+    /// Fail if an earlier state is being viewed, see `view_at`.
+    fn ensure_writable(&self) -> Result<(), PersistenceError> {
+        let pinned = self
+            .pinned_commit
+            .lock()
+            .map_err(|e| PersistenceError::Other(format!("{}", e)))?
+            .is_some();
+        if pinned {
+            return Err(PersistenceError::UnsupportedOperation(
+                "cannot write while viewing an earlier state".into(),
+            ));
+        }
+
+        // A newer app may rely on fields this one does not know and would drop
+        let version = self.stored_schema_version()?;
+        if version > SCHEMA_VERSION {
+            return Err(PersistenceError::UnsupportedSchema {
+                found: version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+        Ok(())
+    }
+
+    /// Schema version of the data that is read, from `group.toml`.
+    fn stored_schema_version(&self) -> Result<u32, PersistenceError> {
+        migrations::schema_version(&self.read_blob_text(Path::new("group.toml"))?)
+    }
+
+    /// Text of a file written with schema version `version`, upgraded to the
+    /// current schema. Files of newer versions are read as they are.
+    fn upgraded(
+        &self,
+        kind: FileKind,
+        text: &str,
+        version: u32,
+    ) -> Result<String, PersistenceError> {
+        if version >= SCHEMA_VERSION {
+            return Ok(text.to_string());
+        }
+        Ok(migrations::migrate(kind, text, version)?.unwrap_or_else(|| text.to_string()))
+    }
+
+    /// Kind of a stored file by its path, `None` for files that are not data.
+    fn file_kind(&self, path_in_repo: &Path) -> Option<FileKind> {
+        if path_in_repo == Path::new("group.toml") {
+            return Some(FileKind::Group);
+        }
+        if !path_in_repo.starts_with(&self.ledgers_root)
+            || path_in_repo.extension().is_none_or(|ext| ext != "toml")
+        {
+            return None;
+        }
+        let name = path_in_repo.file_name()?.to_str()?;
+        if name == ".ledger.toml" {
+            Some(FileKind::Ledger)
+        } else if path_in_repo.parent()?.file_name()? == ".recurring" {
+            Some(FileKind::RecurringTemplate)
+        } else if !name.starts_with('.') {
+            Some(FileKind::Transaction)
+        } else {
+            None
+        }
+    }
+
+    /// Whether schema upgrades are committed on this device, from the
+    /// repository's local git config (`borrowchecker.commitupgrades`).
+    pub fn commits_upgrades(&self) -> bool {
+        self.repo
+            .config()
+            .and_then(|config| config.get_bool("borrowchecker.commitupgrades"))
+            .unwrap_or(false)
+    }
+
+    /// The commit a revision id (full or abbreviated hash) refers to.
+    fn find_revision(&self, revision_id: &str) -> Result<Commit<'_>, PersistenceError> {
+        self.repo
+            .revparse_single(revision_id)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| PersistenceError::NotFound(format!("{}: {}", revision_id, e)))
+    }
+
+    /// Write `content` to `path_in_repo`, or remove the file if `content` is
+    /// `None`, and commit the change on top of main (or HEAD).
+    fn commit_change(
+        &self,
+        path_in_repo: &Path,
+        content: Option<&str>,
+        message: &str,
+    ) -> Result<(), PersistenceError> {
+        self.ensure_writable()?;
+
+        let (ref_name, parent) = self.head_commit()?;
+        let blob = content
+            .map(|content| self.repo.blob(content.as_bytes()))
+            .transpose()?;
+        let components = path_components(path_in_repo)?;
+
+        let root_tree = parent.tree()?;
+        let tree_id = self
+            .write_tree_path(Some(&root_tree), &components, blob)?
+            .ok_or_else(|| PersistenceError::Other("refusing to commit an empty tree".into()))?;
+        let tree = self.repo.find_tree(tree_id)?;
+        self.commit_tree(&ref_name, &parent, &tree, message)
+    }
+
+    /// Commit `tree` on top of `parent` onto the reference `ref_name`.
+    ///
+    /// If the branch is checked out, the working directory is updated first.
+    /// Files with local modifications are never overwritten, the commit fails
+    /// instead.
+    fn commit_tree(
+        &self,
+        ref_name: &str,
+        parent: &Commit<'_>,
+        tree: &Tree<'_>,
+        message: &str,
+    ) -> Result<(), PersistenceError> {
+        let is_checked_out = !self.repo.is_bare()
+            && self
+                .repo
+                .head()
+                .ok()
+                .and_then(|head| head.name().map(|name| name == ref_name))
+                .unwrap_or(false);
+        if is_checked_out {
+            self.repo
+                .checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+                .map_err(|e| {
+                    PersistenceError::Git(format!("failed to update working directory: {}", e))
+                })?;
+        }
+
+        let signature = self.signature()?;
+        self.repo.commit(
+            Some(ref_name),
+            &signature,
+            &signature,
+            message,
+            tree,
+            &[parent],
+        )?;
+        Ok(())
+    }
+
+    /// Return a copy of `tree` with the blob at `components` replaced, or
+    /// removed if `blob` is `None`. Trees left empty are removed as well.
+    fn write_tree_path(
+        &self,
+        tree: Option<&Tree<'_>>,
+        components: &[&str],
+        blob: Option<Oid>,
+    ) -> Result<Option<Oid>, PersistenceError> {
+        let (name, rest) = components
+            .split_first()
+            .ok_or_else(|| PersistenceError::Other("empty path".into()))?;
+        let mut builder = self.repo.treebuilder(tree)?;
+
+        let child = if rest.is_empty() {
+            blob.map(|oid| (oid, FileMode::Blob))
+        } else {
+            let subtree = match tree.and_then(|tree| tree.get_name(name)) {
+                Some(entry) if entry.kind() == Some(ObjectType::Tree) => {
+                    Some(self.repo.find_tree(entry.id())?)
+                }
+                Some(entry) => {
+                    return Err(PersistenceError::InvalidObjectType(format!(
+                        "{} is not a tree (kind={:?})",
+                        name,
+                        entry.kind()
+                    )));
+                }
+                None => None,
+            };
+            self.write_tree_path(subtree.as_ref(), rest, blob)?
+                .map(|oid| (oid, FileMode::Tree))
+        };
+
+        match child {
+            Some((oid, mode)) => {
+                builder.insert(name, oid, mode.into())?;
+            }
+            None => {
+                if builder.get(name)?.is_some() {
+                    builder.remove(name)?;
+                }
+            }
+        }
+
+        if builder.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(builder.write()?))
+        }
+    }
+
+    /// Author and committer of new commits: the local user's entity if set,
+    /// otherwise the git config.
+    fn signature(&self) -> Result<Signature<'static>, PersistenceError> {
+        let author = self
+            .author
+            .lock()
+            .map_err(|e| PersistenceError::Other(format!("{}", e)))?
+            .clone();
+        match author {
+            Some((name, email)) => Ok(Signature::now(&name, &email)?),
+            None => self
+                .repo
+                .signature()
+                .or_else(|_| Signature::now("Borrow Checker", "borrow-checker@localhost"))
+                .map_err(PersistenceError::from),
+        }
+    }
+
     /// Build the ledger map (ledger UUID -> path) by scanning the ledgers folder.
     ///
     /// This will clear and repopulate the internal ledger_map.
@@ -147,6 +633,7 @@ impl PersistenceRepository for GitPersistence {
 
     fn load_group(&self) -> Result<structs::Group, PersistenceError> {
         let text = self.read_blob_text(Path::new("group.toml"))?;
+        let text = self.upgraded(FileKind::Group, &text, migrations::schema_version(&text)?)?;
         let group: structs::Group =
             toml::from_str(&text).map_err(|e| PersistenceError::Toml(format!("{}", e)))?;
         Ok(group)
@@ -164,6 +651,7 @@ impl PersistenceRepository for GitPersistence {
     fn list_ledgers(&self) -> Result<Vec<structs::Ledger>, PersistenceError> {
         // Get root tree
         let root_tree = self.get_root_tree()?;
+        let version = self.stored_schema_version()?;
 
         // Find ledgers root subtree (e.g. "ledgers")
         let ledgers_tree = match self.subtree_from_tree(&root_tree, &self.ledgers_root) {
@@ -230,7 +718,8 @@ impl PersistenceRepository for GitPersistence {
                                 })?;
                             let text = str::from_utf8(blob.content())
                                 .map_err(|e| PersistenceError::Utf8(format!("{}", e)))?;
-                            match toml::from_str::<structs::Ledger>(text) {
+                            let text = self.upgraded(FileKind::Ledger, text, version)?;
+                            match toml::from_str::<structs::Ledger>(&text) {
                                 Ok(ledger) => results.push(ledger),
                                 Err(e) => {
                                     eprintln!(
@@ -305,21 +794,12 @@ impl PersistenceRepository for GitPersistence {
         ledger_id: Uuid,
     ) -> Result<Vec<structs::Transaction>, PersistenceError> {
         // Find ledger path in map
-        let map = self.ledger_map.lock().unwrap();
-        let ledger_path = match map.get(&ledger_id) {
-            Some(p) => p.clone(),
-            None => {
-                return Err(PersistenceError::NotFound(format!(
-                    "ledger id {} not found",
-                    ledger_id
-                )));
-            }
-        };
-        drop(map);
+        let ledger_path = self.ledger_path(ledger_id)?;
 
         // Get root tree and find the subtree for the ledger path
         let root_tree = self.get_root_tree()?;
         let ledger_tree = self.subtree_from_tree(&root_tree, &ledger_path)?;
+        let version = self.stored_schema_version()?;
 
         let mut transactions = Vec::new();
 
@@ -344,7 +824,8 @@ impl PersistenceRepository for GitPersistence {
                     })?;
                     let text = str::from_utf8(blob.content())
                         .map_err(|e| PersistenceError::Utf8(format!("{}", e)))?;
-                    match toml::from_str::<structs::Transaction>(text) {
+                    let text = self.upgraded(FileKind::Transaction, text, version)?;
+                    match toml::from_str::<structs::Transaction>(&text) {
                         Ok(tx) => transactions.push(tx),
                         Err(e) => eprintln!("failed to parse {}: {}", name, e),
                     }
@@ -363,32 +844,403 @@ impl PersistenceRepository for GitPersistence {
 
     fn create_transaction(
         &self,
-        _ledger_id: Uuid,
-        _transaction: structs::Transaction,
+        ledger_id: Uuid,
+        transaction: structs::Transaction,
     ) -> Result<Uuid, PersistenceError> {
-        Err(PersistenceError::UnsupportedOperation(
-            "create_transaction is not implemented for GitPersistence".into(),
-        ))
+        let path = self.transaction_path(ledger_id, transaction.id)?;
+        if self.path_exists(&path)? {
+            return Err(PersistenceError::Other(format!(
+                "transaction {} already exists",
+                transaction.id
+            )));
+        }
+
+        let id = transaction.id;
+        let message = format!("Add transaction {}", transaction.description);
+        let text = canonical::to_string(&transaction, None)?;
+        self.commit_change(&path, Some(&text), &message)?;
+        Ok(id)
+    }
+
+    fn create_transactions(
+        &self,
+        transactions: Vec<(Uuid, structs::Transaction)>,
+        message: &str,
+    ) -> Result<(), PersistenceError> {
+        self.ensure_writable()?;
+
+        let (ref_name, parent) = self.head_commit()?;
+        let mut tree = parent.tree()?;
+        for (ledger_id, transaction) in transactions {
+            let path = self.transaction_path(ledger_id, transaction.id)?;
+            if tree.get_path(&path).is_ok() {
+                return Err(PersistenceError::Other(format!(
+                    "transaction {} already exists",
+                    transaction.id
+                )));
+            }
+            let text = canonical::to_string(&transaction, None)?;
+            let blob = self.repo.blob(text.as_bytes())?;
+            let tree_id = self
+                .write_tree_path(Some(&tree), &path_components(&path)?, Some(blob))?
+                .ok_or_else(|| {
+                    PersistenceError::Other("refusing to commit an empty tree".into())
+                })?;
+            tree = self.repo.find_tree(tree_id)?;
+        }
+        self.commit_tree(&ref_name, &parent, &tree, message)
     }
 
     fn update_transaction(
         &self,
-        _ledger_id: Uuid,
-        _transaction: structs::Transaction,
+        ledger_id: Uuid,
+        transaction: structs::Transaction,
     ) -> Result<(), PersistenceError> {
-        Err(PersistenceError::UnsupportedOperation(
-            "update_transaction is not implemented for GitPersistence".into(),
-        ))
+        let path = self.transaction_path(ledger_id, transaction.id)?;
+        if !self.path_exists(&path)? {
+            return Err(PersistenceError::NotFound(format!(
+                "transaction {} not found",
+                transaction.id
+            )));
+        }
+
+        // Keep comments and formatting, so the diff only shows what changed
+        let existing = self.read_blob_text(&path)?;
+        let message = format!("Update transaction {}", transaction.description);
+        let text = canonical::to_string(&transaction, Some(&existing))?;
+        self.commit_change(&path, Some(&text), &message)
     }
 
     fn delete_transaction(
         &self,
-        _ledger_id: Uuid,
-        _transaction_id: Uuid,
+        ledger_id: Uuid,
+        transaction_id: Uuid,
     ) -> Result<(), PersistenceError> {
-        Err(PersistenceError::UnsupportedOperation(
-            "delete_transaction is not implemented for GitPersistence".into(),
-        ))
+        let path = self.transaction_path(ledger_id, transaction_id)?;
+        if !self.path_exists(&path)? {
+            return Err(PersistenceError::NotFound(format!(
+                "transaction {} not found",
+                transaction_id
+            )));
+        }
+
+        let message = format!("Delete transaction {}", transaction_id);
+        self.commit_change(&path, None, &message)
+    }
+
+    fn list_recurring_templates(
+        &self,
+        ledger_id: Uuid,
+    ) -> Result<Vec<structs::RecurringTemplate>, PersistenceError> {
+        let recurring_path = self.ledger_path(ledger_id)?.join(".recurring");
+
+        // Ledgers without templates have no .recurring folder
+        let root_tree = self.get_root_tree()?;
+        if root_tree.get_path(&recurring_path).is_err() {
+            return Ok(Vec::new());
+        }
+        let recurring_tree = self.subtree_from_tree(&root_tree, &recurring_path)?;
+        let version = self.stored_schema_version()?;
+
+        let mut templates = Vec::new();
+
+        for entry in recurring_tree.iter() {
+            let name = match entry.name() {
+                Some(n) => n,
+                None => {
+                    eprintln!("skipping entry with no name");
+                    continue;
+                }
+            };
+
+            match entry.kind() {
+                Some(ObjectType::Blob) => {
+                    let blob = self.repo.find_blob(entry.id()).map_err(|e| {
+                        PersistenceError::Git(format!("failed to read blob {}: {}", name, e))
+                    })?;
+                    let text = str::from_utf8(blob.content())
+                        .map_err(|e| PersistenceError::Utf8(format!("{}", e)))?;
+                    let text = self.upgraded(FileKind::RecurringTemplate, text, version)?;
+                    match toml::from_str::<structs::RecurringTemplate>(&text) {
+                        Ok(template) => templates.push(template),
+                        Err(e) => eprintln!("failed to parse {}: {}", name, e),
+                    }
+                }
+                Some(kind) => {
+                    eprintln!("skipping non-blob entry {}: {:?}", name, kind);
+                }
+                None => {
+                    eprintln!("entry {} has no object type", name);
+                }
+            }
+        }
+
+        Ok(templates)
+    }
+
+    // ---------------- History Operations ----------------
+
+    fn transaction_history(
+        &self,
+        ledger_id: Uuid,
+        transaction_id: Uuid,
+    ) -> Result<Vec<TransactionRevision>, PersistenceError> {
+        let path = self.transaction_path(ledger_id, transaction_id)?;
+
+        let mut history = Vec::new();
+        for (commit, before, after) in self.file_revisions(&path)? {
+            let before = before.map(|oid| self.blob_text(oid)).transpose()?;
+            let after = after.map(|oid| self.blob_text(oid)).transpose()?;
+            history.push(TransactionRevision {
+                revision: revision(&commit),
+                changes: diff_fields(before.as_deref(), after.as_deref())?,
+            });
+        }
+        Ok(history)
+    }
+
+    fn transaction_at(
+        &self,
+        ledger_id: Uuid,
+        transaction_id: Uuid,
+        revision_id: &str,
+    ) -> Result<Option<structs::Transaction>, PersistenceError> {
+        let path = self.transaction_path(ledger_id, transaction_id)?;
+        let commit = self.find_revision(revision_id)?;
+        match self.blob_id_in_commit(&commit, &path)? {
+            Some(oid) => {
+                let version = self.schema_version_in(&commit)?;
+                Ok(Some(self.transaction_from_blob(oid, version)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn restore_transaction(
+        &self,
+        ledger_id: Uuid,
+        transaction_id: Uuid,
+        revision_id: &str,
+    ) -> Result<(), PersistenceError> {
+        let path = self.transaction_path(ledger_id, transaction_id)?;
+        let commit = self.find_revision(revision_id)?;
+        let content = match self.transaction_at(ledger_id, transaction_id, revision_id)? {
+            Some(transaction) => {
+                // Like an update: keep the current file's comments and formatting
+                let existing = match self.path_exists(&path)? {
+                    true => Some(self.read_blob_text(&path)?),
+                    false => None,
+                };
+                Some(canonical::to_string(&transaction, existing.as_deref())?)
+            }
+            None => None,
+        };
+
+        let message = format!(
+            "Restore transaction {} as of {}",
+            transaction_id,
+            short_id(&commit)
+        );
+        self.commit_change(&path, content.as_deref(), &message)
+    }
+
+    fn revert_revision(&self, revision_id: &str) -> Result<(), PersistenceError> {
+        self.ensure_writable()?;
+
+        let (ref_name, head) = self.head_commit()?;
+        let commit = self.find_revision(revision_id)?;
+        // Merges are reverted relative to the branch they were merged into
+        let mainline = if commit.parent_count() > 1 { 1 } else { 0 };
+        let mut index = self.repo.revert_commit(&commit, &head, mainline, None)?;
+        if index.has_conflicts() {
+            return Err(PersistenceError::Other(format!(
+                "cannot undo {}, it conflicts with later changes",
+                short_id(&commit)
+            )));
+        }
+        let tree = self.repo.find_tree(index.write_tree_to(&self.repo)?)?;
+
+        let message = format!(
+            "Revert \"{}\"\n\nThis reverts commit {}.",
+            commit.summary().unwrap_or_default(),
+            commit.id()
+        );
+        self.commit_tree(&ref_name, &head, &tree, &message)
+    }
+
+    fn current_revision(&self) -> Result<Revision, PersistenceError> {
+        Ok(revision(&self.read_commit()?))
+    }
+
+    fn last_seen_revision(&self, user_id: Uuid) -> Result<Option<String>, PersistenceError> {
+        match self.repo.config()?.get_string(&last_seen_key(user_id)) {
+            Ok(revision_id) => Ok(Some(revision_id)),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn mark_seen(&self, user_id: Uuid, revision_id: &str) -> Result<(), PersistenceError> {
+        // Store the full hash, abbreviations may become ambiguous
+        let commit = self.find_revision(revision_id)?;
+        self.repo
+            .config()?
+            .set_str(&last_seen_key(user_id), &commit.id().to_string())?;
+        Ok(())
+    }
+
+    fn transaction_changes_since(
+        &self,
+        ledger_id: Uuid,
+        revision_id: &str,
+    ) -> Result<Vec<TransactionChange>, PersistenceError> {
+        let ledger_path = self.ledger_path(ledger_id)?;
+        let old_commit = self.find_revision(revision_id)?;
+        let new_commit = self.read_commit()?;
+        let old_tree = old_commit.tree()?;
+        let new_tree = new_commit.tree()?;
+        // Both sides are upgraded from the schema they were written with
+        let old_version = self.schema_version_in(&old_commit)?;
+        let new_version = self.schema_version_in(&new_commit)?;
+        let mut options = DiffOptions::new();
+        options.pathspec(ledger_path.as_path());
+        let diff =
+            self.repo
+                .diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut options))?;
+
+        let read = |file: DiffFile<'_>,
+                    version: u32|
+         -> Result<Option<structs::Transaction>, PersistenceError> {
+            // Added files have no old version and deleted files no new one
+            if file.id().is_zero() {
+                return Ok(None);
+            }
+            match self.transaction_from_blob(file.id(), version) {
+                Ok(transaction) => Ok(Some(transaction)),
+                Err(e) => {
+                    eprintln!("failed to parse {:?}: {}", file.path(), e);
+                    Ok(None)
+                }
+            }
+        };
+
+        let mut changes = Vec::new();
+        for delta in diff.deltas() {
+            // Only transaction files, not the ledger marker or .recurring
+            let is_transaction = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .is_some_and(|path| {
+                    path.parent() == Some(ledger_path.as_path())
+                        && path
+                            .file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(|name| !name.starts_with('.'))
+                });
+            if !is_transaction {
+                continue;
+            }
+
+            let change = TransactionChange {
+                before: read(delta.old_file(), old_version)?,
+                after: read(delta.new_file(), new_version)?,
+            };
+            if change.before.is_some() || change.after.is_some() {
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+
+    fn view_at(&self, at: Option<PointInTime>) -> Result<Option<Revision>, PersistenceError> {
+        let commit = match at {
+            None => None,
+            Some(PointInTime::Revision(revision_id)) => Some(self.find_revision(&revision_id)?),
+            Some(PointInTime::Date(date)) => Some(self.commit_until(date)?),
+        };
+
+        let viewed = commit.as_ref().map(revision);
+        let pin = |commit: Option<Oid>| -> Result<Option<Oid>, PersistenceError> {
+            let mut pinned = self
+                .pinned_commit
+                .lock()
+                .map_err(|e| PersistenceError::Other(format!("{}", e)))?;
+            Ok(std::mem::replace(&mut *pinned, commit))
+        };
+        let previous = pin(commit.map(|commit| commit.id()))?;
+
+        // Ledgers may have been added or renamed since. If they cannot be
+        // read, keep viewing what was viewed before.
+        if let Err(e) = self.build_ledger_map() {
+            pin(previous)?;
+            return Err(e);
+        }
+        Ok(viewed)
+    }
+
+    fn verify_revision(
+        &self,
+        revision_id: &str,
+        group: &structs::Group,
+    ) -> Result<SignatureStatus, PersistenceError> {
+        let commit = self.find_revision(revision_id)?;
+        self.signature_status(commit.id(), group)
+    }
+
+    fn unverified_transactions(
+        &self,
+        ledger_id: Uuid,
+        group: &structs::Group,
+    ) -> Result<UnverifiedTransactions, PersistenceError> {
+        // The whole history is walked, so only once per read commit
+        let read_commit = self.read_commit()?.id();
+        let keys = signing_keys(group);
+        {
+            let mut cache = self
+                .unverified
+                .lock()
+                .map_err(|e| PersistenceError::Other(format!("{}", e)))?;
+            if cache.0 != keys {
+                *cache = (keys, HashMap::new());
+            }
+            if let Some(unverified) = cache.1.get(&(read_commit, ledger_id)) {
+                return Ok(unverified.clone());
+            }
+        }
+
+        let ledger_path = self.ledger_path(ledger_id)?;
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        revwalk.push(read_commit)?;
+
+        // Newest first, so the first unverified change of a transaction wins
+        let mut unverified = HashMap::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            let changed = self.changed_transactions(&commit, &ledger_path)?;
+            if changed.is_empty() {
+                continue;
+            }
+            let status = self.signature_status(commit.id(), group)?;
+            if matches!(status, SignatureStatus::Verified(_)) {
+                continue;
+            }
+            for transaction_id in changed {
+                unverified
+                    .entry(transaction_id)
+                    .or_insert_with(|| (revision(&commit), status.clone()));
+            }
+        }
+
+        let mut cache = self
+            .unverified
+            .lock()
+            .map_err(|e| PersistenceError::Other(format!("{}", e)))?;
+        if cache.0 == signing_keys(group) {
+            cache.1.insert((read_commit, ledger_id), unverified.clone());
+        }
+        Ok(unverified)
     }
 
     // ---------------- Storage Operations ----------------
@@ -398,4 +1250,394 @@ impl PersistenceRepository for GitPersistence {
         self.build_ledger_map()?;
         Ok(crate::traits::RefreshResult { has_changes: true })
     }
+
+    fn upgrade_schema(&self, commit: bool) -> Result<Option<u32>, PersistenceError> {
+        let version = self.stored_schema_version()?;
+        if version > SCHEMA_VERSION {
+            return Err(PersistenceError::UnsupportedSchema {
+                found: version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+        if version == SCHEMA_VERSION || !commit {
+            return Ok((version < SCHEMA_VERSION).then_some(version));
+        }
+        self.ensure_writable()?;
+
+        let (ref_name, parent) = self.head_commit()?;
+        let mut files = Vec::new();
+        parent.tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+            if let (Some(ObjectType::Blob), Some(name)) = (entry.kind(), entry.name()) {
+                files.push((Path::new(root).join(name), entry.id()));
+            }
+            TreeWalkResult::Ok
+        })?;
+
+        // Rewrite the files one by one on top of the current tree
+        let mut tree = parent.tree()?;
+        for (path, oid) in files {
+            let Some(kind) = self.file_kind(&path) else {
+                continue;
+            };
+            let existing = self.blob_text(oid)?;
+            let migrated = migrations::migrate(kind, &existing, version)?;
+            let text = match (kind, migrated) {
+                // group.toml always changes, it records the new version
+                (FileKind::Group, migrated) => {
+                    let mut group: structs::Group =
+                        toml::from_str(migrated.as_deref().unwrap_or(&existing))?;
+                    group.schema_version = SCHEMA_VERSION;
+                    canonical::to_string(&group, Some(&existing))?
+                }
+                (kind, Some(migrated)) => canonical::rewrite(kind, &migrated, &existing)?,
+                (_, None) => continue,
+            };
+            let blob = self.repo.blob(text.as_bytes())?;
+            let tree_id = self
+                .write_tree_path(Some(&tree), &path_components(&path)?, Some(blob))?
+                .ok_or_else(|| {
+                    PersistenceError::Other("refusing to commit an empty tree".into())
+                })?;
+            tree = self.repo.find_tree(tree_id)?;
+        }
+
+        let changes: Vec<String> = migrations::descriptions(version)
+            .into_iter()
+            .map(|description| format!("- {}", description))
+            .collect();
+        let message = format!(
+            "Upgrade data to schema version {}\n\n{}",
+            SCHEMA_VERSION,
+            changes.join("\n")
+        );
+        self.commit_tree(&ref_name, &parent, &tree, &message)?;
+        Ok(Some(version))
+    }
+
+    fn set_author(&self, author: &structs::Entity) -> Result<(), PersistenceError> {
+        // Without an email of its own, the entity commits with the configured one
+        let email = match author.emails.first() {
+            Some(email) => email.clone(),
+            None => self
+                .repo
+                .config()
+                .and_then(|config| config.get_string("user.email"))
+                .unwrap_or_else(|_| String::from("borrow-checker@localhost")),
+        };
+        *self
+            .author
+            .lock()
+            .map_err(|e| PersistenceError::Other(format!("{}", e)))? =
+            Some((author.display_name.clone(), email));
+        Ok(())
+    }
+}
+
+/// Key in the repository's local git config holding the last commit a user
+/// has seen. The local config is not pushed, so this stays on the device.
+fn last_seen_key(user_id: Uuid) -> String {
+    format!("borrowchecker.{}.lastseen", user_id)
+}
+
+/// Components of a path in the repository, which has to be valid UTF-8.
+fn path_components(path_in_repo: &Path) -> Result<Vec<&str>, PersistenceError> {
+    path_in_repo
+        .iter()
+        .map(|component| {
+            component.to_str().ok_or_else(|| {
+                PersistenceError::Utf8(format!("invalid path {}", path_in_repo.display()))
+            })
+        })
+        .collect()
+}
+
+/// Abbreviated hash of a commit, like `git log --oneline`.
+fn short_id(commit: &Commit<'_>) -> String {
+    commit.id().to_string().chars().take(7).collect()
+}
+
+/// Author, time and message of a commit.
+fn revision(commit: &Commit<'_>) -> Revision {
+    Revision {
+        revision_id: commit.id().to_string(),
+        author: commit.author().name().unwrap_or("Unknown").to_string(),
+        email: commit.author().email().unwrap_or_default().to_string(),
+        // Shown in UTC if the offset is garbage, at the epoch if the year is
+        time: dates::datetime_from_timestamp(
+            commit.time().seconds(),
+            commit.time().offset_minutes(),
+        )
+        .or_else(|| dates::datetime_from_timestamp(commit.time().seconds(), 0))
+        .unwrap_or(dates::EPOCH),
+        message: commit.summary().unwrap_or_default().to_string(),
+    }
+}
+
+/// Signing keys of the group's entities, the key of the signature caches.
+fn signing_keys(group: &structs::Group) -> SigningKeys {
+    group
+        .entities
+        .iter()
+        .map(|entity| (entity.id, entity.signing_keys.clone()))
+        .collect()
+}
+
+/// Check a commit signature against the signing keys of the group's
+/// entities, with the same tools `git verify-commit` uses.
+///
+/// SSH signatures are checked with `ssh-keygen` against the entities' public
+/// keys. GPG signatures are checked with `gpg`, so the keys have to be in the
+/// local keyring; the entities list their fingerprints.
+fn verify_signature(
+    signature: &[u8],
+    signed_data: &[u8],
+    group: &structs::Group,
+) -> Result<SignatureStatus, PersistenceError> {
+    let dir = PrivateTempDir::new()?;
+    let signature_path = dir.write("commit.sig", signature)?;
+
+    if signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+        verify_ssh_signature(&signature_path, signed_data, group)
+    } else if signature.starts_with(b"-----BEGIN PGP SIGNATURE-----") {
+        verify_gpg_signature(&signature_path, signed_data, group)
+    } else {
+        // X.509 or anything else nobody in the group can have a key for
+        Ok(SignatureStatus::UnknownKey)
+    }
+}
+
+fn verify_ssh_signature(
+    signature_path: &Path,
+    signed_data: &[u8],
+    group: &structs::Group,
+) -> Result<SignatureStatus, PersistenceError> {
+    // One line per key, with the entity id as principal
+    let allowed_signers: String = group
+        .entities
+        .iter()
+        .flat_map(|entity| {
+            entity
+                .signing_keys
+                .iter()
+                .filter(|key| is_ssh_key(key))
+                .map(move |key| format!("{} namespaces=\"git\" {}\n", entity.id, key.trim()))
+        })
+        .collect();
+    if allowed_signers.is_empty() {
+        return Ok(SignatureStatus::UnknownKey);
+    }
+    // Next to the signature, in the same private directory
+    let allowed_signers_path = signature_path.with_extension("allowed");
+    fs::write(&allowed_signers_path, allowed_signers)?;
+
+    let principals = run(
+        Command::new("ssh-keygen")
+            .args(["-Y", "find-principals", "-f"])
+            .arg(&allowed_signers_path)
+            .arg("-s")
+            .arg(signature_path),
+        b"",
+    );
+    let mut status = Ok(SignatureStatus::UnknownKey);
+    if let Ok(principals) = &principals {
+        let principal = String::from_utf8_lossy(&principals.stdout)
+            .lines()
+            .find_map(|line| line.trim().parse::<Uuid>().ok());
+        if let (true, Some(entity_id)) = (principals.status.success(), principal) {
+            status = run(
+                Command::new("ssh-keygen")
+                    .args(["-Y", "verify", "-n", "git", "-f"])
+                    .arg(&allowed_signers_path)
+                    .arg("-I")
+                    .arg(entity_id.to_string())
+                    .arg("-s")
+                    .arg(signature_path),
+                signed_data,
+            )
+            .map(|output| {
+                if output.status.success() {
+                    SignatureStatus::Verified(entity_id)
+                } else {
+                    SignatureStatus::UnknownKey
+                }
+            });
+        }
+    }
+    principals?;
+    status
+}
+
+fn verify_gpg_signature(
+    signature_path: &Path,
+    signed_data: &[u8],
+    group: &structs::Group,
+) -> Result<SignatureStatus, PersistenceError> {
+    let output = run(
+        Command::new("gpg")
+            .args(["--status-fd=1", "--verify"])
+            .arg(signature_path)
+            .arg("-"),
+        signed_data,
+    )?;
+
+    // [GNUPG:] VALIDSIG <fingerprint> ... <primary key fingerprint>
+    let fingerprints: Vec<String> = str::from_utf8(&output.stdout)?
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .flat_map(|fields| {
+            let fields: Vec<&str> = fields.split_whitespace().collect();
+            [fields.first(), fields.last()]
+                .into_iter()
+                .flatten()
+                .map(|fingerprint| fingerprint.to_uppercase())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let signer = group.entities.iter().find(|entity| {
+        entity.signing_keys.iter().any(|key| {
+            let key: String = key
+                .trim_start_matches("0x")
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_uppercase();
+            // Long key ids are the end of the fingerprint
+            key.len() >= 16
+                && fingerprints
+                    .iter()
+                    .any(|fingerprint| fingerprint.ends_with(&key))
+        })
+    });
+    Ok(match signer {
+        Some(entity) => SignatureStatus::Verified(entity.id),
+        None => SignatureStatus::UnknownKey,
+    })
+}
+
+/// A fresh directory for the files `ssh-keygen` and `gpg` read, only
+/// accessible to the current user and removed with its files when dropped.
+struct PrivateTempDir(PathBuf);
+
+impl PrivateTempDir {
+    fn new() -> Result<Self, PersistenceError> {
+        let path = env::temp_dir().join(format!("borrow-checker-{}", Uuid::new_v4()));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        // Fails if the directory exists, so nobody can prepare it
+        builder.create(&path)?;
+        Ok(Self(path))
+    }
+
+    fn write(&self, name: &str, contents: &[u8]) -> Result<PathBuf, PersistenceError> {
+        let path = self.0.join(name);
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+impl Drop for PrivateTempDir {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.0) {
+            eprintln!("failed to remove {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Whether a signing key is an SSH public key rather than a GPG fingerprint.
+fn is_ssh_key(key: &str) -> bool {
+    let key = key.trim();
+    key.starts_with("ssh-") || key.starts_with("ecdsa-") || key.starts_with("sk-")
+}
+
+/// Run a command with `input` on stdin and collect its output.
+fn run(command: &mut Command, input: &[u8]) -> Result<Output, PersistenceError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| PersistenceError::Io(format!("failed to run {:?}: {}", command, e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+/// Top-level fields that differ between two versions of a TOML file, sorted
+/// by name. A missing version counts as a file without fields.
+fn diff_fields(
+    before: Option<&str>,
+    after: Option<&str>,
+) -> Result<Vec<FieldChange>, PersistenceError> {
+    let parse = |text: Option<&str>| -> Result<toml::Table, PersistenceError> {
+        Ok(text
+            .map(toml::from_str::<toml::Table>)
+            .transpose()?
+            .unwrap_or_default())
+    };
+    let (before, after) = (parse(before)?, parse(after)?);
+
+    let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    Ok(fields
+        .into_iter()
+        .filter(|field| before.get(*field) != after.get(*field))
+        .map(|field| FieldChange {
+            field: field.clone(),
+            old_value: before.get(field).map(|value| value.to_string()),
+            new_value: after.get(field).map(|value| value.to_string()),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_fields() {
+        let before = "description = \"Dinner\"\namount = 12.0\ntip = 1.0\n";
+        let after = "description = \"Dinner\"\namount = 15.5\ntax = 2.0\n";
+
+        let sut = diff_fields(Some(before), Some(after)).unwrap();
+
+        assert_eq!(
+            sut,
+            vec![
+                FieldChange {
+                    field: String::from("amount"),
+                    old_value: Some(String::from("12.0")),
+                    new_value: Some(String::from("15.5")),
+                },
+                FieldChange {
+                    field: String::from("tax"),
+                    old_value: None,
+                    new_value: Some(String::from("2.0")),
+                },
+                FieldChange {
+                    field: String::from("tip"),
+                    old_value: Some(String::from("1.0")),
+                    new_value: None,
+                },
+            ]
+        );
+        assert_eq!(diff_fields(None, Some(after)).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_private_temp_dir_is_removed() {
+        let sut = PrivateTempDir::new().unwrap();
+        let path = sut.write("commit.sig", b"signature").unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&sut.0).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        assert!(path.starts_with(&sut.0));
+        drop(sut);
+        assert!(!path.exists());
+    }
 }
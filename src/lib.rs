@@ -4,11 +4,20 @@ use std::path::Path;
 use crate::git_adapter::GitPersistence;
 use crate::traits::PersistenceRepository;
 
+mod business_logic;
+mod canonical;
 mod commands;
 mod components;
+mod dates;
 mod git_adapter;
+mod lint;
+mod migrations;
+mod money;
+mod recurring;
+mod schemas;
 mod structs;
 mod traits;
+mod validation;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -20,24 +29,79 @@ pub fn run() {
     let ledgers = persistence.list_ledgers().unwrap();
 
     // @todo load from config
+    let user = &group.entities[0];
+    let user_id = user.id;
+
+    // Commits are attributed to the local user's entity
+    if let Err(e) = persistence.set_author(user) {
+        eprintln!("failed to set commit author: {}", e);
+    }
+
+    // Older data is upgraded whenever it is read; committing the upgrade is
+    // opt-in per device
+    let commit_upgrade = persistence.commits_upgrades();
+    match persistence.upgrade_schema(commit_upgrade) {
+        Ok(Some(version)) if commit_upgrade => {
+            eprintln!("upgraded data from schema version {}", version)
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("failed to upgrade data: {}", e),
+    }
+
+    // Book rent, subscriptions etc. that became due since the last start
+    let today = dates::today();
+    for ledger in &ledgers {
+        if let Err(e) = recurring::materialize_due(&persistence, ledger.id, today) {
+            eprintln!(
+                "failed to book recurring expenses of {}: {}",
+                ledger.display_name, e
+            );
+        }
+    }
+
     let ledger_id = ledgers[0].id;
-    let user_id = group.entities[0].id;
     let transactions = persistence.list_transactions(ledger_id).unwrap();
 
+    // Changes are shown since the last visit; on the first start, everything counts as seen
+    if let Ok(None) = persistence.last_seen_revision(user_id) {
+        let marked = persistence
+            .current_revision()
+            .and_then(|current| persistence.mark_seen(user_id, &current.revision_id));
+        if let Err(e) = marked {
+            eprintln!("failed to remember the last seen revision: {}", e);
+        }
+    }
+
     tauri::Builder::default()
         .manage(structs::AppState {
+            persistence: std::sync::Mutex::new(Box::new(persistence)),
             group: std::sync::Mutex::new(group),
             ledgers: std::sync::Mutex::new(ledgers),
             transactions: std::sync::Mutex::new(transactions),
             current_ledger_id: std::sync::Mutex::new(Some(ledger_id)),
             user_id,
+            net_by_household: std::sync::Mutex::new(false),
+            viewed_revision: std::sync::Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             commands::render_header,
             commands::render_ledger_header,
             commands::render_transactions,
             commands::switch_ledger,
-            commands::get_expense
+            commands::toggle_household_netting,
+            commands::get_expense,
+            commands::new_expense,
+            commands::per_night_splits,
+            commands::new_line_item,
+            commands::materialize_recurring,
+            commands::render_group_overview,
+            commands::render_statistics,
+            commands::time_travel,
+            commands::restore_transaction,
+            commands::revert_revision,
+            commands::render_changes,
+            commands::mark_seen,
+            commands::record_group_settlement
         ])
         .setup(|app| {
             if cfg!(debug_assertions) {
@@ -52,3 +116,32 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Check the files of a group repository, e.g. after editing them by hand.
+/// Prints one `file:line: message` line per problem and returns whether there
+/// were none.
+pub fn lint(repo: &Path) -> bool {
+    match lint::lint(repo) {
+        Ok(diagnostics) => {
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic);
+            }
+            diagnostics.is_empty()
+        }
+        Err(e) => {
+            eprintln!("failed to lint {}: {}", repo.display(), e);
+            false
+        }
+    }
+}
+
+/// Regenerate the JSON Schemas of the repository files in `dir`.
+pub fn write_schemas(dir: &Path) -> bool {
+    match schemas::write(dir) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("failed to write schemas to {}: {}", dir.display(), e);
+            false
+        }
+    }
+}
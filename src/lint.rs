@@ -0,0 +1,345 @@
+use crate::git_adapter::GitPersistence;
+use crate::migrations::{self, FileKind, SCHEMA_VERSION};
+use crate::structs::{Group, Ledger, RecurringTemplate, Transaction};
+use crate::traits::{PersistenceError, PersistenceRepository, ValidationError, Validator};
+use crate::validation::GroupValidator;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml_edit::{Document, Item, TableLike, Value};
+use uuid::Uuid;
+
+/// A problem found in a file of a group repository.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    /// Line number, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path.display(), self.line, self.message)
+    }
+}
+
+/// Check the files in the working tree of a group repository, including
+/// uncommitted edits: every file parses and the `Validator` rules hold. If
+/// the group only accepts signed commits, the latest change of every
+/// transaction has to be signed as well.
+pub fn lint(repo: &Path) -> Result<Vec<Diagnostic>, PersistenceError> {
+    let mut diagnostics = Vec::new();
+
+    let group_path = repo.join("group.toml");
+    let group_text = fs::read_to_string(&group_path)?;
+    let version = migrations::schema_version(&group_text).unwrap_or(SCHEMA_VERSION);
+    if version > SCHEMA_VERSION {
+        let error = PersistenceError::UnsupportedSchema {
+            found: version,
+            supported: SCHEMA_VERSION,
+        };
+        diagnostics.push(diagnostic(
+            &group_path,
+            &group_text,
+            "schema_version",
+            error,
+        ));
+        return Ok(diagnostics);
+    }
+
+    let Some(group) = parse::<Group>(&group_path, FileKind::Group, version, &mut diagnostics)?
+    else {
+        return Ok(diagnostics);
+    };
+    let validator = GroupValidator::for_group(&group);
+    report(
+        &group_path,
+        validator.validate_group(&group).errors,
+        &mut diagnostics,
+    )?;
+
+    let mut ledger_dirs = HashMap::new();
+    for dir in sorted_entries(&repo.join("ledgers"))? {
+        let ledger_path = dir.join(".ledger.toml");
+        if !ledger_path.is_file() {
+            continue;
+        }
+        let Some(ledger) =
+            parse::<Ledger>(&ledger_path, FileKind::Ledger, version, &mut diagnostics)?
+        else {
+            continue;
+        };
+        report(
+            &ledger_path,
+            validator.validate_ledger(&ledger, &group).errors,
+            &mut diagnostics,
+        )?;
+
+        for path in sorted_entries(&dir)? {
+            if !is_data_file(&path) {
+                continue;
+            }
+            let parsed =
+                parse::<Transaction>(&path, FileKind::Transaction, version, &mut diagnostics)?;
+            if let Some(transaction) = parsed {
+                let errors = validator
+                    .validate_transaction(&transaction, &ledger, &group)
+                    .errors;
+                report(&path, errors, &mut diagnostics)?;
+            }
+        }
+
+        // Templates have no rules of their own, their occurrences are checked
+        // as transactions
+        let recurring = dir.join(".recurring");
+        if recurring.is_dir() {
+            for path in sorted_entries(&recurring)? {
+                if is_data_file(&path) {
+                    parse::<RecurringTemplate>(
+                        &path,
+                        FileKind::RecurringTemplate,
+                        version,
+                        &mut diagnostics,
+                    )?;
+                }
+            }
+        }
+        ledger_dirs.insert(ledger.id, dir);
+    }
+
+    if group.signed_commits_only {
+        lint_signatures(repo, &group, &validator, &ledger_dirs, &mut diagnostics)?;
+    }
+    Ok(diagnostics)
+}
+
+/// Report transactions whose latest change is not signed by an entity, from
+/// the committed history.
+fn lint_signatures(
+    repo: &Path,
+    group: &Group,
+    validator: &GroupValidator,
+    ledger_dirs: &HashMap<Uuid, PathBuf>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), PersistenceError> {
+    let persistence = GitPersistence::new(Some(repo.to_path_buf()))?;
+    persistence.refresh()?;
+
+    let mut ledgers: Vec<(&Uuid, &PathBuf)> = ledger_dirs.iter().collect();
+    ledgers.sort_by(|a, b| a.1.cmp(b.1));
+    for (ledger_id, dir) in ledgers {
+        let mut unverified: Vec<_> = persistence
+            .unverified_transactions(*ledger_id, group)?
+            .into_iter()
+            .collect();
+        unverified.sort_by_key(|(transaction_id, _)| *transaction_id);
+
+        for (transaction_id, (revision, status)) in unverified {
+            let path = dir.join(format!("{}.toml", transaction_id));
+            let Err(error) = validator.validate_signature(&revision, &status) else {
+                continue;
+            };
+            // Deleted since, or not in the working tree
+            let Ok(text) = fs::read_to_string(&path) else {
+                continue;
+            };
+            diagnostics.push(diagnostic(
+                &path,
+                &text,
+                "",
+                format!(
+                    "revision {}: {}",
+                    error.field.get(..7).unwrap_or(&error.field),
+                    error.message
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Read and parse a file, upgraded from schema `version` like the app reads
+/// it. A file that does not parse is reported and gives `None`.
+fn parse<T: DeserializeOwned>(
+    path: &Path,
+    kind: FileKind,
+    version: u32,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Option<T>, PersistenceError> {
+    let text = fs::read_to_string(path)?;
+    let upgraded = match migrations::migrate(kind, &text, version) {
+        Ok(upgraded) => upgraded,
+        Err(e) => {
+            diagnostics.push(diagnostic(path, &text, "", e));
+            return Ok(None);
+        }
+    };
+
+    match toml::from_str::<T>(upgraded.as_deref().unwrap_or(&text)) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) => {
+            // Lines refer to the file as written. If the upgrade changed the
+            // text, look for the problem in the original.
+            let span = match upgraded {
+                None => e.span(),
+                Some(_) => toml::from_str::<T>(&text).err().and_then(|e| e.span()),
+            };
+            diagnostics.push(Diagnostic {
+                path: path.to_path_buf(),
+                line: span.map_or(1, |span| line_number(&text, span.start)),
+                message: e.message().trim().to_string(),
+            });
+            Ok(None)
+        }
+    }
+}
+
+/// Add validation errors of a file, at the lines of their fields.
+fn report(
+    path: &Path,
+    errors: Vec<ValidationError>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), PersistenceError> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let text = fs::read_to_string(path)?;
+    for error in errors {
+        let message = format!("{}: {}", error.field, error.message);
+        diagnostics.push(diagnostic(path, &text, &error.field, message));
+    }
+    Ok(())
+}
+
+fn diagnostic(path: &Path, text: &str, field: &str, message: impl fmt::Display) -> Diagnostic {
+    Diagnostic {
+        path: path.to_path_buf(),
+        line: line_of(text, field),
+        message: message.to_string(),
+    }
+}
+
+/// Line of the field a validation error refers to, e.g.
+/// `split_ratios[1].entity_id`, or of the closest enclosing field that is
+/// written in the file. 1 if none is.
+fn line_of(text: &str, field: &str) -> usize {
+    let Ok(document) = Document::parse(text) else {
+        return 1;
+    };
+
+    let mut span: Option<Range<usize>> = None;
+    let mut table: Option<&dyn TableLike> = Some(document.as_table());
+    for segment in field.split('.').filter(|s| !s.is_empty()) {
+        let (name, index) = match segment.split_once('[') {
+            Some((name, index)) => (name, index.trim_end_matches(']').parse::<usize>().ok()),
+            None => (segment, None),
+        };
+        let Some((key, item)) = table.and_then(|t| t.get_key_value(name)) else {
+            break;
+        };
+        span = key.span().or(span);
+        table = item.as_table_like();
+
+        let Some(index) = index else {
+            continue;
+        };
+        match item {
+            Item::ArrayOfTables(tables) => {
+                let Some(element) = tables.get(index) else {
+                    break;
+                };
+                span = element.span().or(span);
+                table = Some(element as &dyn TableLike);
+            }
+            Item::Value(Value::Array(array)) => {
+                let Some(element) = array.get(index) else {
+                    break;
+                };
+                span = element.span().or(span);
+                table = element.as_inline_table().map(|t| t as &dyn TableLike);
+            }
+            _ => break,
+        }
+    }
+    span.map_or(1, |span| line_number(text, span.start))
+}
+
+fn line_number(text: &str, offset: usize) -> usize {
+    text.get(..offset).unwrap_or(text).matches('\n').count() + 1
+}
+
+/// Files and folders in `dir`, by name.
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, PersistenceError> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// TOML files that are not hidden, like the ones the app reads.
+fn is_data_file(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_none_or(|name| name.starts_with('.'));
+    !hidden && path.is_file() && path.extension().is_some_and(|ext| ext == "toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSACTION: &str = r#"id = "019b5b3b-25e7-7e53-a0b6-0af3afde297c"
+description = "Dinner"
+paid_by_entity = "c8744a29-7ed0-447a-af5a-51e4ad291d1d"
+currency_iso_4217 = "EUR"
+amount = 60.0
+transaction_datetime_rfc_3339 = 2025-12-27T12:00:00Z
+
+[[split_ratios]]
+entity_id = "c8744a29-7ed0-447a-af5a-51e4ad291d1d"
+shares = 1
+
+[[split_ratios]]
+entity_id = "3abaaf40-a35a-488d-8ef2-0184c8c5f3c3"
+shares = 1
+"#;
+
+    #[test]
+    fn test_line_of_field() {
+        assert_eq!(line_of(TRANSACTION, "currency_iso_4217"), 4);
+        assert_eq!(line_of(TRANSACTION, "split_ratios"), 8);
+        assert_eq!(line_of(TRANSACTION, "split_ratios[1].entity_id"), 13);
+        // Not written, the closest enclosing field is used
+        assert_eq!(line_of(TRANSACTION, "split_ratios[1].percent"), 12);
+        assert_eq!(line_of(TRANSACTION, "to_entity"), 1);
+    }
+
+    #[test]
+    fn test_parse_error_line_of_version_0_group() {
+        let path = std::env::temp_dir().join(format!("borrow-checker-{}.toml", Uuid::new_v4()));
+        let group = "# Our flat\n\n[[entities]]\nid = \"c8744a29-7ed0-447a-af5a-51e4ad291d1d\"\ndisplay_name = 42\n";
+        fs::write(&path, group).unwrap();
+        let mut diagnostics = Vec::new();
+
+        let sut = parse::<Group>(&path, FileKind::Group, 0, &mut diagnostics);
+        fs::remove_file(&path).unwrap();
+
+        assert!(sut.unwrap().is_none());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 5);
+    }
+
+    #[test]
+    fn test_line_of_inline_array() {
+        let ledger = "id = \"019b5b3b-25e7-7e53-a0b6-0af3afde297c\"\nparticipants = [\n  \"c8744a29-7ed0-447a-af5a-51e4ad291d1d\",\n  { entity_id = \"3abaaf40-a35a-488d-8ef2-0184c8c5f3c3\", to = 2025-07-03 },\n]\n";
+
+        assert_eq!(line_of(ledger, "participants[0]"), 3);
+        assert_eq!(line_of(ledger, "participants[1].to"), 4);
+    }
+}
@@ -1,6 +1,23 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::path::Path;
+
 fn main() {
-  app_lib::run();
+  let args: Vec<String> = std::env::args().collect();
+  let ok = match args.get(1).map(String::as_str) {
+    // `borrow-checker lint [repository]`, the current directory by default
+    Some("lint") => app_lib::lint(Path::new(args.get(2).map_or(".", String::as_str))),
+    // `borrow-checker schemas [directory]`
+    Some("schemas") => app_lib::write_schemas(Path::new(
+      args.get(2).map_or("schemas", String::as_str),
+    )),
+    _ => {
+      app_lib::run();
+      true
+    }
+  };
+  if !ok {
+    std::process::exit(1);
+  }
 }
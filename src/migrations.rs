@@ -0,0 +1,122 @@
+use crate::traits::PersistenceError;
+
+/// Schema version of the files this version of the app reads and writes.
+///
+/// Stored as `schema_version` in `group.toml`. Files without it are version 0,
+/// written before the version was recorded.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The kinds of stored files, which migrations may treat differently.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FileKind {
+    Group,
+    Ledger,
+    Transaction,
+    RecurringTemplate,
+}
+
+/// Upgrade of the stored files from one schema version to the next.
+///
+/// Files written by this app while the upgrade was not committed are already
+/// in the newer format, so migrations must leave such files unchanged.
+pub struct Migration {
+    /// Version the migration upgrades from, it produces `from + 1`
+    pub from: u32,
+    /// What changes, used in the upgrade commit
+    pub description: &'static str,
+    /// Rewrite one file, `None` if it stays as it is
+    pub migrate: fn(FileKind, &str) -> Result<Option<String>, String>,
+}
+
+/// All migrations, oldest first, one per version.
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "Record the schema version in group.toml",
+    // The version is written with every group, nothing else changed
+    migrate: |_, _| Ok(None),
+}];
+
+/// Schema version of a `group.toml`, 0 if it has none.
+pub fn schema_version(group_text: &str) -> Result<u32, PersistenceError> {
+    let table: toml::Table = toml::from_str(group_text)?;
+    match table.get("schema_version") {
+        None => Ok(0),
+        Some(toml::Value::Integer(version)) => u32::try_from(*version).map_err(|_| {
+            PersistenceError::Migration(format!("invalid schema version {}", version))
+        }),
+        Some(value) => Err(PersistenceError::Migration(format!(
+            "invalid schema version {}",
+            value
+        ))),
+    }
+}
+
+/// Upgrade the text of a file written with schema version `from` to
+/// `SCHEMA_VERSION`. The version in `group.toml` is left as it is, writing
+/// the group records the new one.
+///
+/// Returns `None` if the file stays as it is.
+pub fn migrate(kind: FileKind, text: &str, from: u32) -> Result<Option<String>, PersistenceError> {
+    if from > SCHEMA_VERSION {
+        return Err(PersistenceError::UnsupportedSchema {
+            found: from,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    let mut upgraded: Option<String> = None;
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        let current = upgraded.as_deref().unwrap_or(text);
+        let next = (migration.migrate)(kind, current).map_err(|e| {
+            PersistenceError::Migration(format!("{}: {}", migration.description, e))
+        })?;
+        if next.is_some() {
+            upgraded = next;
+        }
+    }
+    Ok(upgraded)
+}
+
+/// Descriptions of the migrations from version `from` on, for commit messages.
+pub fn descriptions(from: u32) -> Vec<&'static str> {
+    MIGRATIONS
+        .iter()
+        .filter(|m| m.from >= from)
+        .map(|m| m.description)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical;
+    use crate::structs::Group;
+
+    const GROUP: &str = "# Our flat\n\n[[entities]]\nid = \"c8744a29-7ed0-447a-af5a-51e4ad291d1d\"\ndisplay_name = \"Ana\"\n";
+
+    #[test]
+    fn test_upgrade_records_schema_version() {
+        assert_eq!(schema_version(GROUP).unwrap(), 0);
+        let migrated = migrate(FileKind::Group, GROUP, 0).unwrap();
+        let mut group: Group = toml::from_str(migrated.as_deref().unwrap_or(GROUP)).unwrap();
+        group.schema_version = SCHEMA_VERSION;
+
+        let upgraded = canonical::to_string(&group, Some(GROUP)).unwrap();
+
+        assert!(upgraded.starts_with("schema_version = 1\n# Our flat\n"));
+        assert_eq!(schema_version(&upgraded).unwrap(), SCHEMA_VERSION);
+        assert!(
+            migrate(FileKind::Group, &upgraded, SCHEMA_VERSION)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        assert!(matches!(
+            migrate(FileKind::Transaction, "", SCHEMA_VERSION + 1),
+            Err(PersistenceError::UnsupportedSchema { .. })
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+use rational::Rational;
+use std::fmt;
+
+/// A monetary amount stored as integer minor units of an ISO 4217 currency.
+///
+/// `Money { minor_units: 1234, currency: "EUR" }` is EUR 12.34, while
+/// `Money { minor_units: 1234, currency: "JPY" }` is JPY 1234.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Money {
+    minor_units: i64,
+    currency: String,
+}
+
+/// Number of decimal places (minor unit exponent) of an ISO 4217 currency.
+///
+/// Unknown codes fall back to 2, which is what most currencies use.
+pub fn minor_unit_exponent(currency: &str) -> u32 {
+    match currency {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "UYI" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        "CLF" | "UYW" => 4,
+        _ => 2,
+    }
+}
+
+impl Money {
+    pub fn new(minor_units: i64, currency: impl Into<String>) -> Self {
+        Self {
+            minor_units,
+            currency: currency.into(),
+        }
+    }
+
+    pub fn zero(currency: impl Into<String>) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Convert a decimal amount in major units (e.g. `12.34` EUR) to money.
+    ///
+    /// Used to read the float amounts stored in the TOML files. The value is
+    /// rounded to the nearest minor unit of the currency.
+    pub fn from_major(amount: f64, currency: impl Into<String>) -> Self {
+        let currency = currency.into();
+        let factor = 10f64.powi(minor_unit_exponent(&currency) as i32);
+        Self::new((amount * factor).round() as i64, currency)
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        self.minor_units
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Decimal amount in major units, used when writing the TOML files.
+    pub fn to_major(&self) -> f64 {
+        self.minor_units as f64 / 10f64.powi(minor_unit_exponent(&self.currency) as i32)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor_units == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor_units < 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.minor_units > 0
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::new(self.minor_units.abs(), self.currency.clone())
+    }
+
+    pub fn negated(&self) -> Self {
+        Self::new(-self.minor_units, self.currency.clone())
+    }
+
+    /// Add two amounts, returning `None` if the currencies differ.
+    pub fn checked_add(&self, other: &Money) -> Option<Self> {
+        if self.currency != other.currency {
+            return None;
+        }
+        Some(Self::new(
+            self.minor_units.checked_add(other.minor_units)?,
+            self.currency.clone(),
+        ))
+    }
+
+    /// The amount without currency code, with the currency's decimal places
+    /// (e.g. `"12.34"`, `"1500"`, `"-0.125"`).
+    pub fn format_amount(&self) -> String {
+        let exponent = minor_unit_exponent(&self.currency);
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+        let units = self.minor_units.unsigned_abs();
+        if exponent == 0 {
+            return format!("{}{}", sign, units);
+        }
+        let factor = 10u64.pow(exponent);
+        format!(
+            "{}{}.{:0width$}",
+            sign,
+            units / factor,
+            units % factor,
+            width = exponent as usize
+        )
+    }
+
+    /// Smallest representable step of the currency, for `<input step=..>`.
+    pub fn input_step(&self) -> String {
+        Money::new(1, self.currency.clone()).format_amount()
+    }
+}
+
+/// Scale ratios to integers with the same proportions (`1/3, 1/6` -> `2, 1`).
+///
+/// Negative ratios are treated as zero.
+pub fn integer_weights<'a>(ratios: impl IntoIterator<Item = &'a Rational>) -> Vec<i128> {
+    let fractions: Vec<(i128, i128)> = ratios
+        .into_iter()
+        .map(|ratio| {
+            let denominator = ratio.denominator();
+            (
+                ratio.numerator() * denominator.signum(),
+                denominator.abs().max(1),
+            )
+        })
+        .collect();
+    let common_denominator = fractions.iter().fold(1i128, |acc, (_, denominator)| {
+        acc / gcd(acc, *denominator) * denominator
+    });
+    fractions
+        .iter()
+        .map(|(numerator, denominator)| (numerator * (common_denominator / denominator)).max(0))
+        .collect()
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+/// Split `total` between keys proportionally to their ratios so that the parts
+/// sum exactly to `total`.
+///
+/// Every key first gets the floor of its exact share. The minor units left over
+/// go one each to the keys with the largest remainders, ties broken by the
+/// smaller key, so every device computes identical numbers regardless of the
+/// order of `ratios`. The result is sorted by key. If all ratios are zero,
+/// every key gets zero.
+pub fn allocate<K: Ord + Copy>(total: &Money, ratios: &[(K, Rational)]) -> Vec<(K, Money)> {
+    let weights = integer_weights(ratios.iter().map(|(_, ratio)| ratio));
+    let weight_sum: i128 = weights.iter().sum();
+    let magnitude = total.minor_units.unsigned_abs() as i128;
+    let sign = total.minor_units.signum() as i128;
+
+    let mut parts: Vec<(K, i128, i128)> = ratios
+        .iter()
+        .zip(&weights)
+        .map(|((key, _), weight)| {
+            if weight_sum == 0 {
+                (*key, 0, 0)
+            } else {
+                let exact = magnitude * weight;
+                (*key, exact / weight_sum, exact % weight_sum)
+            }
+        })
+        .collect();
+
+    let allocated: i128 = parts.iter().map(|(_, floor, _)| floor).sum();
+    let mut leftover = if weight_sum == 0 {
+        0
+    } else {
+        magnitude - allocated
+    };
+
+    parts.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+    for part in parts.iter_mut() {
+        if leftover == 0 {
+            break;
+        }
+        part.1 += 1;
+        leftover -= 1;
+    }
+
+    parts.sort_by(|a, b| a.0.cmp(&b.0));
+    parts
+        .into_iter()
+        .map(|(key, units, _)| (key, Money::new((units * sign) as i64, total.currency())))
+        .collect()
+}
+
+impl fmt::Display for Money {
+    /// Formats as `"EUR 12.34"` or `"-EUR 12.34"`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_negative() {
+            write!(f, "-{} {}", self.currency, self.abs().format_amount())
+        } else {
+            write!(f, "{} {}", self.currency, self.format_amount())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_major_rounds_to_minor_units() {
+        assert_eq!(Money::from_major(12.34, "EUR").minor_units(), 1234);
+        assert_eq!(Money::from_major(0.1 + 0.2, "EUR").minor_units(), 30);
+        assert_eq!(Money::from_major(1500.0, "JPY").minor_units(), 1500);
+        assert_eq!(Money::from_major(1.234, "KWD").minor_units(), 1234);
+    }
+
+    #[test]
+    fn test_to_major_round_trips() {
+        for minor in [0, 1, 99, 1234, -1234, 100_000_001] {
+            let money = Money::new(minor, "EUR");
+            assert_eq!(Money::from_major(money.to_major(), "EUR"), money);
+        }
+    }
+
+    #[test]
+    fn test_format_uses_currency_decimals() {
+        assert_eq!(Money::new(1234, "EUR").to_string(), "EUR 12.34");
+        assert_eq!(Money::new(-5, "EUR").to_string(), "-EUR 0.05");
+        assert_eq!(Money::new(1500, "JPY").to_string(), "JPY 1500");
+        assert_eq!(Money::new(1234, "KWD").to_string(), "KWD 1.234");
+    }
+
+    #[test]
+    fn test_allocate_sums_exactly_to_total() {
+        let third = Rational::new(1, 3);
+        let sut = allocate(
+            &Money::new(1000, "EUR"),
+            &[(3, third), (1, third), (2, third)],
+        );
+
+        assert_eq!(
+            sut,
+            vec![
+                (1, Money::new(334, "EUR")),
+                (2, Money::new(333, "EUR")),
+                (3, Money::new(333, "EUR")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_allocate_gives_leftover_to_largest_remainder() {
+        let sut = allocate(
+            &Money::new(100, "EUR"),
+            &[(1, Rational::new(1, 6)), (2, Rational::new(5, 6))],
+        );
+
+        // exact shares are 16.67 and 83.33
+        assert_eq!(
+            sut,
+            vec![(1, Money::new(17, "EUR")), (2, Money::new(83, "EUR"))]
+        );
+    }
+
+    #[test]
+    fn test_allocate_negative_total() {
+        let half = Rational::new(1, 2);
+        let sut = allocate(&Money::new(-5, "EUR"), &[(1, half), (2, half)]);
+
+        assert_eq!(
+            sut,
+            vec![(1, Money::new(-3, "EUR")), (2, Money::new(-2, "EUR"))]
+        );
+    }
+
+    #[test]
+    fn test_allocate_unnormalized_ratios() {
+        let sut = allocate(
+            &Money::new(900, "JPY"),
+            &[(1, Rational::new(2, 1)), (2, Rational::new(1, 1))],
+        );
+
+        assert_eq!(
+            sut,
+            vec![(1, Money::new(600, "JPY")), (2, Money::new(300, "JPY"))]
+        );
+    }
+
+    #[test]
+    fn test_checked_add_rejects_mixed_currencies() {
+        let eur = Money::new(100, "EUR");
+        assert_eq!(eur.checked_add(&eur), Some(Money::new(200, "EUR")));
+        assert_eq!(eur.checked_add(&Money::new(100, "CHF")), None);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::dates::{date_from_day_number, day_number, days_in_month};
+use crate::structs::{Frequency, RecurringTemplate, Transaction, TransactionKind};
+use crate::traits::{PersistenceError, PersistenceRepository};
+use std::collections::HashSet;
+use toml::value::{Date, Datetime};
+use uuid::Uuid;
+
+/// Turn every occurrence of the ledger's recurring templates up to `until`
+/// into a transaction, unless it already exists.
+///
+/// Occurrence ids only depend on the template and the date, so running this
+/// on several devices (or twice on one) never creates duplicates.
+///
+/// Returns the number of transactions created.
+pub fn materialize_due(
+    persistence: &dyn PersistenceRepository,
+    ledger_id: Uuid,
+    until: Date,
+) -> Result<usize, PersistenceError> {
+    let templates = persistence.list_recurring_templates(ledger_id)?;
+    if templates.is_empty() {
+        return Ok(0);
+    }
+
+    let existing: HashSet<Uuid> = persistence
+        .list_transactions(ledger_id)?
+        .iter()
+        .map(|transaction| transaction.id)
+        .collect();
+
+    let mut created = 0;
+    for template in &templates {
+        for date in occurrence_dates(template, until) {
+            let transaction = occurrence(template, date);
+            if !existing.contains(&transaction.id) {
+                persistence.create_transaction(ledger_id, transaction)?;
+                created += 1;
+            }
+        }
+    }
+    Ok(created)
+}
+
+/// Dates of all occurrences of a template from its start up to and including
+/// `until` (or its end date, if earlier).
+pub fn occurrence_dates(template: &RecurringTemplate, until: Date) -> Vec<Date> {
+    let schedule = &template.schedule;
+    let last = match schedule.end_date {
+        Some(end_date) if day_number(end_date) < day_number(until) => end_date,
+        _ => until,
+    };
+
+    let mut dates = Vec::new();
+    for n in 0.. {
+        let date = match nth_date(
+            schedule.start_date,
+            schedule.frequency,
+            schedule.interval * n,
+        ) {
+            Some(date) => date,
+            None => break,
+        };
+        if day_number(date) > day_number(last) {
+            break;
+        }
+        dates.push(date);
+    }
+    dates
+}
+
+/// The transaction for the occurrence of a template on `date`.
+pub fn occurrence(template: &RecurringTemplate, date: Date) -> Transaction {
+    Transaction {
+        id: occurrence_id(template.id, date),
+        description: template.description.clone(),
+        kind: TransactionKind::Expense,
+        paid_by_entity: template.paid_by_entity,
+        payers: Vec::new(),
+        amount: template.amount.clone(),
+        transaction_datetime_rfc_3339: occurrence_datetime(date),
+        split_ratios: template.split_ratios.clone(),
+        line_items: Vec::new(),
+        tax: None,
+        tip: None,
+        category: template.category.clone(),
+        tags: Vec::new(),
+    }
+}
+
+/// Stable id of an occurrence, a name-based UUID in the template's namespace.
+pub fn occurrence_id(template_id: Uuid, date: Date) -> Uuid {
+    Uuid::new_v5(&template_id, date.to_string().as_bytes())
+}
+
+/// Noon UTC, so the occurrence shows up on `date` in every time zone that
+/// is within 12 hours of UTC.
+fn occurrence_datetime(date: Date) -> Datetime {
+    format!("{}T12:00:00Z", date)
+        .parse()
+        .expect("a valid date at noon UTC is a valid datetime")
+}
+
+/// The `n`th step of `frequency` after `start`, `None` once past year 9999.
+fn nth_date(start: Date, frequency: Frequency, n: u32) -> Option<Date> {
+    let date = match frequency {
+        Frequency::Daily => date_from_day_number(day_number(start) + n as i64),
+        Frequency::Weekly => date_from_day_number(day_number(start) + 7 * n as i64),
+        Frequency::Monthly => {
+            let months = start.year as i64 * 12 + (start.month as i64 - 1) + n as i64;
+            clamped_date(
+                months.div_euclid(12),
+                months.rem_euclid(12) as u8 + 1,
+                start.day,
+            )
+        }
+        Frequency::Yearly => clamped_date(start.year as i64 + n as i64, start.month, start.day),
+    };
+    (date.year <= 9999).then_some(date)
+}
+
+/// The given day, or the last day of the month if it is shorter.
+fn clamped_date(year: i64, month: u8, day: u8) -> Date {
+    Date {
+        year: year.clamp(0, u16::MAX as i64) as u16,
+        month,
+        day: day.min(days_in_month(year, month)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Money;
+    use crate::structs::Schedule;
+
+    fn date(year: u16, month: u8, day: u8) -> Date {
+        Date { year, month, day }
+    }
+
+    fn template(
+        frequency: Frequency,
+        start_date: Date,
+        end_date: Option<Date>,
+    ) -> RecurringTemplate {
+        RecurringTemplate {
+            id: Uuid::from_u128(1),
+            description: String::from("Rent"),
+            paid_by_entity: Uuid::from_u128(2),
+            amount: Money::new(120_000, "EUR"),
+            schedule: Schedule {
+                frequency,
+                interval: 1,
+                start_date,
+                end_date,
+            },
+            split_ratios: Vec::new(),
+            category: None,
+        }
+    }
+
+    #[test]
+    fn test_monthly_keeps_day_of_month() {
+        let sut = template(Frequency::Monthly, date(2025, 1, 31), None);
+
+        assert_eq!(
+            occurrence_dates(&sut, date(2025, 4, 30)),
+            vec![
+                date(2025, 1, 31),
+                date(2025, 2, 28),
+                date(2025, 3, 31),
+                date(2025, 4, 30)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_end_date_stops_occurrences() {
+        let sut = template(Frequency::Weekly, date(2025, 1, 1), Some(date(2025, 1, 20)));
+
+        assert_eq!(
+            occurrence_dates(&sut, date(2025, 12, 31)),
+            vec![date(2025, 1, 1), date(2025, 1, 8), date(2025, 1, 15)]
+        );
+    }
+
+    #[test]
+    fn test_occurrence_ids_are_stable() {
+        let sut = template(Frequency::Monthly, date(2025, 1, 1), None);
+
+        let first = occurrence(&sut, date(2025, 2, 1));
+        let second = occurrence(&sut, date(2025, 2, 1));
+
+        assert_eq!(first.id, second.id);
+        assert_ne!(first.id, occurrence(&sut, date(2025, 3, 1)).id);
+        assert_eq!(
+            first.transaction_datetime_rfc_3339.to_string(),
+            "2025-02-01T12:00:00Z"
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use crate::structs::{Group, Ledger, RecurringTemplate, Transaction};
+use schemars::Schema;
+use schemars::generate::SchemaSettings;
+use schemars::transform::RestrictFormats;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// JSON Schemas of the files in a group repository, by file name stem.
+///
+/// They describe the files as written on disk, so editors such as VS Code
+/// (with taplo) can check hand-edited TOML. The generated schemas are shipped
+/// in `schemas/`, see `.taplo.toml` for the files they apply to.
+pub fn schemas() -> Vec<(&'static str, Schema)> {
+    // Only standard formats, editors do not know Rust's `uint32` or `double`
+    let mut generator = SchemaSettings::draft2020_12()
+        .with_transform(RestrictFormats::default())
+        .into_generator();
+    vec![
+        ("group", generator.root_schema_for::<Group>()),
+        ("ledger", generator.root_schema_for::<Ledger>()),
+        ("transaction", generator.root_schema_for::<Transaction>()),
+        (
+            "recurring",
+            generator.root_schema_for::<RecurringTemplate>(),
+        ),
+    ]
+}
+
+/// Text of a schema file.
+fn to_json(schema: &Schema) -> String {
+    let mut text = serde_json::to_string_pretty(schema).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+/// Write every schema to `<name>.schema.json` in `dir`.
+pub fn write(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, schema) in schemas() {
+        fs::write(dir.join(format!("{}.schema.json", name)), to_json(&schema))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_schemas_are_up_to_date() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas");
+        for (name, schema) in schemas() {
+            let path = dir.join(format!("{}.schema.json", name));
+            let shipped = fs::read_to_string(&path).unwrap_or_default();
+            assert!(
+                shipped == to_json(&schema),
+                "{} is outdated, regenerate it with `cargo run -- schemas`",
+                path.display()
+            );
+        }
+    }
+}
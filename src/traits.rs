@@ -1,8 +1,12 @@
-use crate::structs::{Entity, Group, Ledger, LedgerWithTransactions, Split, Transaction};
+use crate::money::Money;
+use crate::structs::{
+    Entity, Group, Ledger, LedgerWithTransactions, RecurringTemplate, Split, Transaction,
+};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::path::Path;
+use toml::value::{Date, Datetime};
 use uuid::Uuid;
 
 // ============================================================================
@@ -42,6 +46,12 @@ pub enum PersistenceError {
         message: String,
     },
 
+    /// A stored file could not be upgraded to the current schema version
+    Migration(String),
+
+    /// The stored data uses a newer schema version than this app understands
+    UnsupportedSchema { found: u32, supported: u32 },
+
     /// Generic other error with free-form message
     Other(String),
 }
@@ -63,6 +73,12 @@ impl fmt::Display for PersistenceError {
             } => {
                 write!(f, "Failed to parse ledger '{}': {}", ledger_name, message)
             }
+            PersistenceError::Migration(s) => write!(f, "Migration error: {}", s),
+            PersistenceError::UnsupportedSchema { found, supported } => write!(
+                f,
+                "Data uses schema version {}, but this app only supports up to {}; please update the app",
+                found, supported
+            ),
             PersistenceError::Other(s) => write!(f, "{}", s),
         }
     }
@@ -89,14 +105,44 @@ impl From<toml::de::Error> for PersistenceError {
     }
 }
 
+impl From<toml::ser::Error> for PersistenceError {
+    fn from(e: toml::ser::Error) -> Self {
+        PersistenceError::Toml(format!("{}", e))
+    }
+}
+
 impl From<git2::Error> for PersistenceError {
     fn from(e: git2::Error) -> Self {
         PersistenceError::Git(format!("{}", e))
     }
 }
 
+/// Errors that can occur in business logic calculations
 #[derive(Debug)]
-pub enum BusinessLogicError {}
+pub enum BusinessLogicError {
+    /// The transactions of the requested ledger were not provided
+    LedgerNotFound(Uuid),
+
+    /// Amounts in different currencies cannot be added up
+    CurrencyMismatch { expected: String, found: String },
+}
+
+impl fmt::Display for BusinessLogicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusinessLogicError::LedgerNotFound(id) => write!(f, "Ledger not found: {}", id),
+            BusinessLogicError::CurrencyMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Currency mismatch: expected {}, found {}",
+                    expected, found
+                )
+            }
+        }
+    }
+}
+
+impl Error for BusinessLogicError {}
 
 // ============================================================================
 // Result Types and Supporting Structures
@@ -116,10 +162,73 @@ pub struct Settlement {
     pub from_entity: Uuid,
     /// Who receives
     pub to_entity: Uuid,
-    /// How much
-    pub amount: f64,
-    /// Currency code
-    pub currency: String,
+    /// How much, in the currency of the settled balances
+    pub amount: Money,
+}
+
+/// A change to the stored data (a commit for git)
+#[derive(Debug, Clone)]
+pub struct Revision {
+    /// Storage-specific id of the revision (the commit hash for git)
+    pub revision_id: String,
+    /// Name of whoever made the change
+    pub author: String,
+    /// Email address of whoever made the change, to map it to an entity
+    pub email: String,
+    /// When the change was made
+    pub time: Datetime,
+    /// Description of the change (the commit message for git)
+    pub message: String,
+}
+
+/// One version of a transaction in the storage history
+#[derive(Debug)]
+pub struct TransactionRevision {
+    pub revision: Revision,
+    /// Fields that differ from the previous version, sorted by name
+    pub changes: Vec<FieldChange>,
+}
+
+/// A transaction that was added (no `before`), deleted (no `after`) or
+/// edited between two revisions
+pub struct TransactionChange {
+    pub before: Option<Transaction>,
+    pub after: Option<Transaction>,
+}
+
+/// An earlier state of the stored data to read from
+#[derive(Debug)]
+pub enum PointInTime {
+    /// Right after the given revision
+    Revision(String),
+    /// The end of a day: the newest revision made on or before it, by the
+    /// local date of the revision's time
+    Date(Date),
+}
+
+/// Who signed a revision, as far as the signing keys in the group tell
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureStatus {
+    /// Validly signed with a key of this entity
+    Verified(Uuid),
+    /// Signed, but not validly or not with a key of any entity
+    UnknownKey,
+    /// Not signed at all
+    Unsigned,
+    /// Signed, but the signature cannot be checked on this device, e.g.
+    /// because `ssh-keygen` or `gpg` is missing
+    Unverifiable,
+}
+
+/// A field of a stored file that was added, changed or removed
+#[derive(Debug, PartialEq)]
+pub struct FieldChange {
+    /// Name of the top-level field (e.g. "amount")
+    pub field: String,
+    /// Value before the change, as written in the file (`None` if added)
+    pub old_value: Option<String>,
+    /// Value after the change, as written in the file (`None` if removed)
+    pub new_value: Option<String>,
 }
 
 /// Result of validation operations
@@ -157,6 +266,8 @@ pub enum ValidationErrorType {
     DuplicateValue,
     /// Sum mismatch (e.g., ratios don't sum to 1)
     SumMismatch,
+    /// Change is not signed by an entity of the group
+    Unverified,
 }
 
 // ============================================================================
@@ -213,6 +324,14 @@ pub trait PersistenceRepository {
         transaction: Transaction,
     ) -> Result<Uuid, PersistenceError>;
 
+    /// Add new transactions to several ledgers, as (ledger id, transaction),
+    /// in one change: either all of them are stored or none
+    fn create_transactions(
+        &self,
+        transactions: Vec<(Uuid, Transaction)>,
+        message: &str,
+    ) -> Result<(), PersistenceError>;
+
     /// Modify an existing transaction
     fn update_transaction(
         &self,
@@ -227,12 +346,107 @@ pub trait PersistenceRepository {
         transaction_id: Uuid,
     ) -> Result<(), PersistenceError>;
 
+    /// Get the recurring expense templates of a ledger
+    fn list_recurring_templates(
+        &self,
+        ledger_id: Uuid,
+    ) -> Result<Vec<RecurringTemplate>, PersistenceError>;
+
+    // ------------------------------------------------------------------------
+    // History Operations
+    // ------------------------------------------------------------------------
+
+    /// Every change made to a transaction, newest first
+    ///
+    /// The oldest revision is its creation. If the transaction was deleted,
+    /// the newest revision removes all of its fields.
+    fn transaction_history(
+        &self,
+        ledger_id: Uuid,
+        transaction_id: Uuid,
+    ) -> Result<Vec<TransactionRevision>, PersistenceError>;
+
+    /// A transaction as it was right after a revision, upgraded to the
+    /// current schema, `None` if it did not exist at that revision
+    fn transaction_at(
+        &self,
+        ledger_id: Uuid,
+        transaction_id: Uuid,
+        revision_id: &str,
+    ) -> Result<Option<Transaction>, PersistenceError>;
+
+    /// Write a transaction as it was right after a revision, as a new change
+    ///
+    /// It is written like any other update, in the current schema and
+    /// keeping the formatting of the current file. If the transaction did not
+    /// exist at that revision, it is deleted.
+    fn restore_transaction(
+        &self,
+        ledger_id: Uuid,
+        transaction_id: Uuid,
+        revision_id: &str,
+    ) -> Result<(), PersistenceError>;
+
+    /// Undo all changes of a revision with a new change, keeping the history
+    fn revert_revision(&self, revision_id: &str) -> Result<(), PersistenceError>;
+
+    /// The revision of the data that is currently read
+    fn current_revision(&self) -> Result<Revision, PersistenceError>;
+
+    /// The revision a user last looked at on this device, if any
+    fn last_seen_revision(&self, user_id: Uuid) -> Result<Option<String>, PersistenceError>;
+
+    /// Remember on this device that a user has seen everything up to a revision
+    fn mark_seen(&self, user_id: Uuid, revision_id: &str) -> Result<(), PersistenceError>;
+
+    /// Transactions of a ledger that changed between a revision and the
+    /// current revision
+    fn transaction_changes_since(
+        &self,
+        ledger_id: Uuid,
+        revision_id: &str,
+    ) -> Result<Vec<TransactionChange>, PersistenceError>;
+
+    /// Read all data as of an earlier point in time, or the latest data again
+    /// with `None`
+    ///
+    /// Returns the revision that is now read, `None` for the latest data.
+    /// While reading an earlier point in time, all writes fail.
+    fn view_at(&self, at: Option<PointInTime>) -> Result<Option<Revision>, PersistenceError>;
+
+    /// Check the signature of a revision against the signing keys of the
+    /// group's entities
+    fn verify_revision(
+        &self,
+        revision_id: &str,
+        group: &Group,
+    ) -> Result<SignatureStatus, PersistenceError>;
+
+    /// Transactions of a ledger that were changed by a revision without a
+    /// verified signature, with the newest such revision and its status
+    fn unverified_transactions(
+        &self,
+        ledger_id: Uuid,
+        group: &Group,
+    ) -> Result<HashMap<Uuid, (Revision, SignatureStatus)>, PersistenceError>;
+
     // ------------------------------------------------------------------------
     // Storage Operations
     // ------------------------------------------------------------------------
 
     /// Refreshes local data from remote storage
     fn refresh(&self) -> Result<RefreshResult, PersistenceError>;
+
+    /// Upgrade data written with an older schema version
+    ///
+    /// Older files are always upgraded in memory when they are read. With
+    /// `commit`, the upgrade is also stored, so other devices see it too.
+    /// Returns the version of the stored data before the upgrade, `None` if
+    /// it was up to date.
+    fn upgrade_schema(&self, commit: bool) -> Result<Option<u32>, PersistenceError>;
+
+    /// Attribute all further changes to an entity, the local user
+    fn set_author(&self, author: &Entity) -> Result<(), PersistenceError>;
 }
 
 // ============================================================================
@@ -264,6 +478,7 @@ pub trait Validator {
     /// - Display name is not empty
     /// - All participants exist in group
     /// - Participants list is not empty
+    /// - No participant leaves before arriving
     fn validate_ledger(&self, ledger: &Ledger, group: &Group) -> ValidationResult;
 
     /// Validate all aspects of a transaction
@@ -271,10 +486,17 @@ pub trait Validator {
     /// Checks:
     /// - Transaction ID is present
     /// - Paid-by entity exists in group and is ledger participant
-    /// - All split entities exist in group and are ledger participants
-    /// - Split ratios are positive and sum to ~1 (within tolerance)
+    /// - If there are several payers: all exist in group, are ledger participants,
+    ///   the paid-by entity is the first of them, and their amounts sum to the amount
+    /// - Expenses and unlinked refunds have splits or line items, line items have splits
+    /// - All split entities exist in group, are ledger participants and are not pots
+    /// - All splits use the same split mode and add up (see validate_split_ratios_sum)
+    /// - Transfers have a recipient that exists in group, is a ledger participant
+    ///   and differs from the paid-by entity, and have no splits or line items
+    /// - Refunds do not refund themselves
+    /// - Line items do not exceed the amount, and items, tax and tip add up to it
     /// - Currency code is valid ISO 4217
-    /// - Amount is positive
+    /// - Amount is positive, except for refunds and income where it is negative
     /// - Description is not empty
     /// - Datetime is valid
     fn validate_transaction(
@@ -298,8 +520,32 @@ pub trait Validator {
     /// Validate ISO 4217 currency codes (3-letter codes)
     fn validate_currency(&self, code: &str) -> Result<(), ValidationError>;
 
-    /// Ensure sum of all ratios equals 1 (within tolerance of 0.001)
-    fn validate_split_ratios_sum(&self, ratios: &[Split]) -> Result<(), ValidationError>;
+    /// Ensure the splits of a transaction add up, depending on their mode:
+    /// - Ratios sum to exactly 1
+    /// - Percentages sum to exactly 100
+    /// - Exact amounts sum to `amount`
+    /// - Shares are not all zero
+    /// - Adjustments do not exceed `amount`
+    fn validate_split_ratios_sum(
+        &self,
+        amount: &Money,
+        splits: &[Split],
+    ) -> Result<(), ValidationError>;
+
+    // ------------------------------------------------------------------------
+    // Revision-level Validation
+    // ------------------------------------------------------------------------
+
+    /// Validate who signed a revision
+    ///
+    /// Checks, only if the signed commits only policy is enabled:
+    /// - The revision is signed
+    /// - The signature is valid and made with a key of an entity of the group
+    fn validate_signature(
+        &self,
+        revision: &Revision,
+        signature: &SignatureStatus,
+    ) -> Result<(), ValidationError>;
 }
 
 // ============================================================================
@@ -321,25 +567,46 @@ pub trait BusinessLogic {
     /// Algorithm: For each transaction:
     /// - If user paid: they are owed by each other participant for their share
     /// - If user didn't pay: they owe the payer their share
+    ///
+    /// Implementations may net balances per household, in which case the map
+    /// is keyed by household UUID for members of a household.
     fn calculate_balances(
         &self,
         ledger_id: Uuid,
         user_id: Uuid,
-    ) -> Result<HashMap<Uuid, f64>, BusinessLogicError>;
+    ) -> Result<HashMap<Uuid, Money>, BusinessLogicError>;
+
+    /// Calculate each entity's net balance in a ledger
+    ///
+    /// Returns a map of entity UUID to amount where:
+    /// - Positive values = the entity is owed money
+    /// - Negative values = the entity owes money
+    ///
+    /// The values of all entities sum to zero. Keyed by household UUID for
+    /// members of a household if balances are netted per household.
+    fn calculate_net_balances(
+        &self,
+        ledger_id: Uuid,
+    ) -> Result<HashMap<Uuid, Money>, BusinessLogicError>;
 
     /// Calculate a user's share of a transaction
     ///
-    /// Algorithm: Find user's ratio in split_ratios, multiply by transaction amount
-    /// Returns user's share amount (0.0 if user not in split)
-    fn get_user_share(&self, transaction: &Transaction, user_id: Uuid) -> f64;
+    /// Algorithm: Split the transaction amount by split_ratios, allocating
+    /// indivisible minor units by largest remainder (ties go to the smaller
+    /// entity id), so the shares of all entities sum exactly to the amount.
+    /// Returns user's share amount (zero if user not in split)
+    fn get_user_share(&self, transaction: &Transaction, user_id: Uuid) -> Money;
 
-    /// Normalize split ratios to sum to 1
+    /// Normalize splits of any mode to ratios that sum to 1
     ///
-    /// Algorithm: Calculate total ratio, divide each by total
-    fn normalize_split_ratios(&self, ratios: Vec<Split>) -> Vec<Split>;
+    /// Algorithm: Turn each split into a weight (ratio, percentage, number of
+    /// shares, exact amount, or equal part of `amount` plus adjustment),
+    /// then divide each weight by the total
+    fn normalize_split_ratios(&self, amount: &Money, splits: Vec<Split>) -> Vec<Split>;
 
     /// Optimize debt settlement (who pays whom, minimizing transactions)
     ///
+    /// Takes net balances as returned by `calculate_net_balances`.
     /// Returns list of settlements (from_entity, to_entity, amount)
-    fn calculate_settlements(&self, balances: HashMap<Uuid, f64>) -> Vec<Settlement>;
+    fn calculate_settlements(&self, balances: HashMap<Uuid, Money>) -> Vec<Settlement>;
 }
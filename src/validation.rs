@@ -0,0 +1,762 @@
+use crate::dates::day_number;
+use crate::money::Money;
+use crate::structs::{Group, Ledger, Split, SplitShare, Transaction, TransactionKind};
+use crate::traits::{
+    Revision, SignatureStatus, ValidationError, ValidationErrorType, ValidationResult, Validator,
+};
+use rational::Rational;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Validator for the data of a group, with optional policies.
+///
+/// Collects every problem instead of stopping at the first one, so a file can
+/// be fixed in one go.
+pub struct GroupValidator {
+    /// Whether changes have to be signed by an entity of the group
+    signed_commits_only: bool,
+}
+
+impl GroupValidator {
+    pub fn new() -> Self {
+        Self {
+            signed_commits_only: false,
+        }
+    }
+
+    /// Reject revisions without a verified signature of an entity.
+    pub fn signed_commits_only(mut self, signed_commits_only: bool) -> Self {
+        self.signed_commits_only = signed_commits_only;
+        self
+    }
+
+    /// The policies the group configured for itself.
+    pub fn for_group(group: &Group) -> Self {
+        Self::new().signed_commits_only(group.signed_commits_only)
+    }
+
+    /// Splits of one list: entities exist, take part in the ledger, are not
+    /// pots, and the shares add up.
+    fn check_splits(
+        &self,
+        field: &str,
+        amount: &Money,
+        splits: &[Split],
+        ledger: &Ledger,
+        group: &Group,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (i, split) in splits.iter().enumerate() {
+            let field = format!("{}[{}].entity_id", field, i);
+            if let Err(e) = self.validate_participant(&field, split.entity_id, ledger, group) {
+                errors.push(e);
+            } else if group.is_pot(split.entity_id) {
+                errors.push(error(
+                    field,
+                    "a pot cannot carry a share",
+                    ValidationErrorType::InvalidValue,
+                ));
+            }
+        }
+        if let Err(mut e) = self.validate_split_ratios_sum(amount, splits) {
+            e.field = field.to_string();
+            errors.push(e);
+        }
+    }
+
+    /// The entity exists and takes part in the ledger.
+    fn validate_participant(
+        &self,
+        field: &str,
+        entity_id: Uuid,
+        ledger: &Ledger,
+        group: &Group,
+    ) -> Result<(), ValidationError> {
+        self.validate_entity_reference(entity_id, group)
+            .map_err(|mut e| {
+                e.field = field.to_string();
+                e
+            })?;
+        if !ledger.participants.iter().any(|p| p.entity_id == entity_id) {
+            return Err(error(
+                field,
+                format!(
+                    "{} does not take part in {}",
+                    entity_id, ledger.display_name
+                ),
+                ValidationErrorType::InvalidReference,
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for GroupValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Validator for GroupValidator {
+    fn validate_group(&self, group: &Group) -> ValidationResult {
+        let mut errors = Vec::new();
+        if group.entities.is_empty() {
+            errors.push(error(
+                "entities",
+                "a group needs at least one entity",
+                ValidationErrorType::MissingField,
+            ));
+        }
+
+        let mut ids = HashSet::new();
+        for (i, entity) in group.entities.iter().enumerate() {
+            if !ids.insert(entity.id) {
+                errors.push(error(
+                    format!("entities[{}].id", i),
+                    format!("{} is used by several entities", entity.id),
+                    ValidationErrorType::DuplicateValue,
+                ));
+            }
+            if entity.display_name.trim().is_empty() {
+                errors.push(error(
+                    format!("entities[{}].display_name", i),
+                    "display name is empty",
+                    ValidationErrorType::MissingField,
+                ));
+            }
+        }
+        result(errors)
+    }
+
+    fn validate_ledger(&self, ledger: &Ledger, group: &Group) -> ValidationResult {
+        let mut errors = Vec::new();
+        if ledger.id.is_nil() {
+            errors.push(error(
+                "id",
+                "ledger id is missing",
+                ValidationErrorType::MissingField,
+            ));
+        }
+        if ledger.display_name.trim().is_empty() {
+            errors.push(error(
+                "display_name",
+                "display name is empty",
+                ValidationErrorType::MissingField,
+            ));
+        }
+        if ledger.participants.is_empty() {
+            errors.push(error(
+                "participants",
+                "a ledger needs at least one participant",
+                ValidationErrorType::MissingField,
+            ));
+        }
+
+        for (i, participant) in ledger.participants.iter().enumerate() {
+            if let Err(mut e) = self.validate_entity_reference(participant.entity_id, group) {
+                e.field = format!("participants[{}]", i);
+                errors.push(e);
+            }
+            match (participant.from, participant.to) {
+                (Some(from), Some(to)) if day_number(to) < day_number(from) => {
+                    errors.push(error(
+                        format!("participants[{}].to", i),
+                        format!("leaves on {} before arriving on {}", to, from),
+                        ValidationErrorType::InvalidValue,
+                    ));
+                }
+                _ => {}
+            }
+        }
+        result(errors)
+    }
+
+    fn validate_transaction(
+        &self,
+        transaction: &Transaction,
+        ledger: &Ledger,
+        group: &Group,
+    ) -> ValidationResult {
+        let mut errors = Vec::new();
+        if transaction.id.is_nil() {
+            errors.push(error(
+                "id",
+                "transaction id is missing",
+                ValidationErrorType::MissingField,
+            ));
+        }
+        if transaction.description.trim().is_empty() {
+            errors.push(error(
+                "description",
+                "description is empty",
+                ValidationErrorType::MissingField,
+            ));
+        }
+        if let Err(mut e) = self.validate_currency(transaction.amount.currency()) {
+            e.field = String::from("currency_iso_4217");
+            errors.push(e);
+        }
+        let datetime = &transaction.transaction_datetime_rfc_3339;
+        if datetime.date.is_none() || datetime.time.is_none() {
+            errors.push(error(
+                "transaction_datetime_rfc_3339",
+                format!("{} is not a date and time", datetime),
+                ValidationErrorType::InvalidFormat,
+            ));
+        }
+
+        // Refunds and income are received, everything else is paid
+        let is_refund = matches!(transaction.kind, TransactionKind::Refund { .. });
+        if is_refund && !transaction.amount.is_negative() {
+            errors.push(error(
+                "amount",
+                "a refund has a negative amount",
+                ValidationErrorType::InvalidValue,
+            ));
+        } else if !is_refund && !transaction.amount.is_positive() {
+            errors.push(error(
+                "amount",
+                "amount must be positive",
+                ValidationErrorType::InvalidValue,
+            ));
+        }
+
+        if let Err(e) =
+            self.validate_participant("paid_by_entity", transaction.paid_by_entity, ledger, group)
+        {
+            errors.push(e);
+        }
+        if !transaction.payers.is_empty() {
+            for (i, payer) in transaction.payers.iter().enumerate() {
+                let field = format!("payers[{}].entity_id", i);
+                if let Err(e) = self.validate_participant(&field, payer.entity_id, ledger, group) {
+                    errors.push(e);
+                }
+            }
+            if transaction.payers[0].entity_id != transaction.paid_by_entity {
+                errors.push(error(
+                    "paid_by_entity",
+                    "must be the first of the payers",
+                    ValidationErrorType::InvalidValue,
+                ));
+            }
+            let paid = transaction
+                .payers
+                .iter()
+                .try_fold(Money::zero(transaction.amount.currency()), |sum, payer| {
+                    sum.checked_add(&payer.amount)
+                });
+            if paid.as_ref() != Some(&transaction.amount) {
+                errors.push(error(
+                    "payers",
+                    format!("payments do not add up to {}", transaction.amount),
+                    ValidationErrorType::SumMismatch,
+                ));
+            }
+        }
+
+        match transaction.kind {
+            TransactionKind::Transfer { to_entity } => {
+                if let Err(e) = self.validate_participant("to_entity", to_entity, ledger, group) {
+                    errors.push(e);
+                }
+                if to_entity == transaction.paid_by_entity {
+                    errors.push(error(
+                        "to_entity",
+                        "a transfer needs a different sender and recipient",
+                        ValidationErrorType::InvalidValue,
+                    ));
+                }
+                if !transaction.split_ratios.is_empty() || !transaction.line_items.is_empty() {
+                    errors.push(error(
+                        "split_ratios",
+                        "a transfer has no splits or line items",
+                        ValidationErrorType::InvalidValue,
+                    ));
+                }
+            }
+            TransactionKind::Refund {
+                original_transaction_id,
+            } => {
+                if original_transaction_id == Some(transaction.id) {
+                    errors.push(error(
+                        "original_transaction_id",
+                        "a refund cannot refund itself",
+                        ValidationErrorType::InvalidReference,
+                    ));
+                }
+            }
+            TransactionKind::Expense => {}
+        }
+
+        // Without shares the payer is owed money nobody owes, and the balances
+        // of the ledger no longer add up to zero. Linked refunds are shared
+        // like the transaction they refund.
+        let is_shared = matches!(
+            transaction.kind,
+            TransactionKind::Expense
+                | TransactionKind::Refund {
+                    original_transaction_id: None
+                }
+        );
+        if is_shared && transaction.split_ratios.is_empty() && transaction.line_items.is_empty() {
+            errors.push(error(
+                "split_ratios",
+                "nobody shares this transaction, it needs splits or line items",
+                ValidationErrorType::MissingField,
+            ));
+        }
+
+        self.check_splits(
+            "split_ratios",
+            &transaction.amount,
+            &transaction.split_ratios,
+            ledger,
+            group,
+            &mut errors,
+        );
+        for (i, item) in transaction.line_items.iter().enumerate() {
+            if item.splits.is_empty() {
+                errors.push(error(
+                    format!("line_items[{}].splits", i),
+                    "nobody shares this line item",
+                    ValidationErrorType::MissingField,
+                ));
+            }
+            self.check_splits(
+                &format!("line_items[{}].splits", i),
+                &item.amount,
+                &item.splits,
+                ledger,
+                group,
+                &mut errors,
+            );
+        }
+        if !transaction.line_items.is_empty() {
+            let itemized = transaction
+                .line_items
+                .iter()
+                .try_fold(Money::zero(transaction.amount.currency()), |sum, item| {
+                    sum.checked_add(&item.amount)
+                });
+            let billed = [&transaction.tax, &transaction.tip]
+                .into_iter()
+                .flatten()
+                .fold(itemized.clone(), |sum, extra| sum?.checked_add(extra));
+            if itemized.is_none_or(|itemized| {
+                itemized.minor_units().abs() > transaction.amount.minor_units().abs()
+            }) {
+                errors.push(error(
+                    "line_items",
+                    format!("line items add up to more than {}", transaction.amount),
+                    ValidationErrorType::SumMismatch,
+                ));
+            } else if billed.as_ref() != Some(&transaction.amount) {
+                errors.push(error(
+                    "line_items",
+                    format!(
+                        "line items, tax and tip do not add up to {}",
+                        transaction.amount
+                    ),
+                    ValidationErrorType::SumMismatch,
+                ));
+            }
+        }
+        result(errors)
+    }
+
+    fn validate_entity_reference(
+        &self,
+        entity_id: Uuid,
+        group: &Group,
+    ) -> Result<(), ValidationError> {
+        if group.entities.iter().any(|e| e.id == entity_id) {
+            Ok(())
+        } else {
+            Err(error(
+                "entity_id",
+                format!("{} is not an entity of the group", entity_id),
+                ValidationErrorType::InvalidReference,
+            ))
+        }
+    }
+
+    fn validate_currency(&self, code: &str) -> Result<(), ValidationError> {
+        if code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase()) {
+            Ok(())
+        } else {
+            Err(error(
+                "currency",
+                format!("{:?} is not an ISO 4217 currency code", code),
+                ValidationErrorType::InvalidFormat,
+            ))
+        }
+    }
+
+    fn validate_split_ratios_sum(
+        &self,
+        amount: &Money,
+        splits: &[Split],
+    ) -> Result<(), ValidationError> {
+        let Some(first) = splits.first() else {
+            return Ok(());
+        };
+        let mode = first.share.mode();
+        if splits.iter().any(|split| split.share.mode() != mode) {
+            return Err(error(
+                "split_ratios",
+                "all splits must use the same mode",
+                ValidationErrorType::InvalidValue,
+            ));
+        }
+
+        let mismatch = |message: String| {
+            Err(error(
+                "split_ratios",
+                message,
+                ValidationErrorType::SumMismatch,
+            ))
+        };
+        let sum_of = |expected: Rational| {
+            splits
+                .iter()
+                .filter_map(|split| match split.share {
+                    SplitShare::Ratio(ratio) | SplitShare::Percentage(ratio) => Some(ratio),
+                    _ => None,
+                })
+                .fold(Rational::new(0, 1), |sum, ratio| sum + ratio)
+                == expected
+        };
+        let money_sum = || {
+            splits
+                .iter()
+                .try_fold(Money::zero(amount.currency()), |sum, split| {
+                    match &split.share {
+                        SplitShare::Exact(part) | SplitShare::Adjustment(part) => {
+                            sum.checked_add(part)
+                        }
+                        _ => Some(sum),
+                    }
+                })
+        };
+
+        match first.share {
+            SplitShare::Ratio(_) if !sum_of(Rational::new(1, 1)) => {
+                mismatch(String::from("ratios do not add up to 1"))
+            }
+            SplitShare::Percentage(_) if !sum_of(Rational::new(100, 1)) => {
+                mismatch(String::from("percentages do not add up to 100"))
+            }
+            SplitShare::Exact(_) if money_sum().as_ref() != Some(amount) => {
+                mismatch(format!("amounts do not add up to {}", amount))
+            }
+            SplitShare::Shares(_)
+                if splits
+                    .iter()
+                    .all(|split| split.share == SplitShare::Shares(0)) =>
+            {
+                mismatch(String::from("at least one share must be above zero"))
+            }
+            SplitShare::Adjustment(_)
+                if money_sum()
+                    .is_none_or(|sum| sum.minor_units().abs() > amount.minor_units().abs()) =>
+            {
+                mismatch(format!("adjustments exceed {}", amount))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_signature(
+        &self,
+        revision: &Revision,
+        signature: &SignatureStatus,
+    ) -> Result<(), ValidationError> {
+        if !self.signed_commits_only {
+            return Ok(());
+        }
+        let message = match signature {
+            SignatureStatus::Verified(_) => return Ok(()),
+            SignatureStatus::UnknownKey => "is not signed by a key of the group",
+            SignatureStatus::Unsigned => "is not signed",
+            SignatureStatus::Unverifiable => "cannot be verified on this device",
+        };
+        Err(error(
+            revision.revision_id.clone(),
+            format!("change by {} {}", revision.author, message),
+            ValidationErrorType::Unverified,
+        ))
+    }
+}
+
+fn error(
+    field: impl Into<String>,
+    message: impl Into<String>,
+    error_type: ValidationErrorType,
+) -> ValidationError {
+    ValidationError {
+        field: field.into(),
+        message: message.into(),
+        error_type,
+    }
+}
+
+fn result(errors: Vec<ValidationError>) -> ValidationResult {
+    ValidationResult {
+        is_valid: errors.is_empty(),
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates;
+
+    const ANA: &str = "c8744a29-7ed0-447a-af5a-51e4ad291d1d";
+    const BEN: &str = "5a0b6a3e-8f4c-4d3b-9d7e-2f1c0b9a8e7d";
+
+    fn group() -> Group {
+        toml::from_str(&format!(
+            r#"
+            [[entities]]
+            id = "{ANA}"
+            display_name = "Ana"
+
+            [[entities]]
+            id = "{BEN}"
+            display_name = "Ben"
+            "#
+        ))
+        .unwrap()
+    }
+
+    fn ledger() -> Ledger {
+        toml::from_str(&format!(
+            r#"
+            id = "00000000-0000-0000-0000-000000000001"
+            display_name = "Trip"
+            participants = ["{ANA}", "{BEN}"]
+            "#
+        ))
+        .unwrap()
+    }
+
+    /// A transaction paid by Ana, `fields` are added to the TOML
+    fn transaction(fields: &str) -> Transaction {
+        toml::from_str(&format!(
+            r#"
+            id = "00000000-0000-0000-0000-000000000002"
+            description = "Dinner"
+            paid_by_entity = "{ANA}"
+            currency_iso_4217 = "EUR"
+            transaction_datetime_rfc_3339 = 2025-12-27T12:00:00Z
+            {fields}
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_duplicate_entity_ids() {
+        let mut sut = group();
+        sut.entities[1].id = sut.entities[0].id;
+
+        let result = GroupValidator::new().validate_group(&sut);
+
+        assert!(!result.is_valid);
+        assert_eq!(result.errors[0].field, "entities[1].id");
+    }
+
+    #[test]
+    fn test_split_sums() {
+        let sut = GroupValidator::new();
+        let amount = Money::new(1000, "EUR");
+        let split = |share| Split {
+            entity_id: Uuid::from_u128(1),
+            share,
+        };
+
+        let half = SplitShare::Ratio(Rational::new(1, 2));
+        assert!(
+            sut.validate_split_ratios_sum(&amount, &[split(half.clone()), split(half.clone())])
+                .is_ok()
+        );
+        assert!(
+            sut.validate_split_ratios_sum(&amount, &[split(half)])
+                .is_err()
+        );
+        assert!(
+            sut.validate_split_ratios_sum(
+                &amount,
+                &[split(SplitShare::Exact(Money::new(600, "EUR")))]
+            )
+            .is_err()
+        );
+        assert!(
+            sut.validate_split_ratios_sum(
+                &amount,
+                &[
+                    split(SplitShare::Shares(1)),
+                    split(SplitShare::Percentage(Rational::new(50, 1)))
+                ]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_payments_add_up() {
+        let payers = |ben: f64| {
+            transaction(&format!(
+                r#"
+                amount = 30.0
+
+                [[payers]]
+                entity_id = "{ANA}"
+                amount = 20.0
+
+                [[payers]]
+                entity_id = "{BEN}"
+                amount = {ben:.1}
+
+                [[split_ratios]]
+                entity_id = "{ANA}"
+                shares = 1
+                "#
+            ))
+        };
+        let (group, ledger) = (group(), ledger());
+        let sut = GroupValidator::new();
+
+        assert!(
+            sut.validate_transaction(&payers(10.0), &ledger, &group)
+                .is_valid
+        );
+        let result = sut.validate_transaction(&payers(5.0), &ledger, &group);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].field, "payers");
+    }
+
+    #[test]
+    fn test_line_items_add_up() {
+        let bill = |amount: f64| {
+            transaction(&format!(
+                r#"
+                amount = {amount:.1}
+                tip = 2.0
+
+                [[line_items]]
+                description = "Pizza"
+                amount = 12.0
+
+                [[line_items.splits]]
+                entity_id = "{BEN}"
+                shares = 1
+                "#
+            ))
+        };
+        let (group, ledger) = (group(), ledger());
+        let sut = GroupValidator::new();
+
+        assert!(
+            sut.validate_transaction(&bill(14.0), &ledger, &group)
+                .is_valid
+        );
+        let result = sut.validate_transaction(&bill(13.0), &ledger, &group);
+        assert_eq!(result.errors[0].field, "line_items");
+        let result = sut.validate_transaction(&bill(10.0), &ledger, &group);
+        assert_eq!(
+            result.errors[0].message,
+            "line items add up to more than EUR 10.00"
+        );
+    }
+
+    #[test]
+    fn test_expense_needs_splits() {
+        let (group, ledger) = (group(), ledger());
+        let sut = GroupValidator::new();
+
+        let result = sut.validate_transaction(&transaction("amount = 10.0"), &ledger, &group);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].field, "split_ratios");
+
+        let refund = transaction("kind = \"refund\"\namount = -10.0");
+        let result = sut.validate_transaction(&refund, &ledger, &group);
+        assert_eq!(result.errors[0].field, "split_ratios");
+    }
+
+    #[test]
+    fn test_transfer_to_self() {
+        let transfer = |to: &str| {
+            transaction(&format!(
+                "kind = \"transfer\"\nto_entity = \"{to}\"\namount = 10.0"
+            ))
+        };
+        let (group, ledger) = (group(), ledger());
+        let sut = GroupValidator::new();
+
+        assert!(
+            sut.validate_transaction(&transfer(BEN), &ledger, &group)
+                .is_valid
+        );
+        let result = sut.validate_transaction(&transfer(ANA), &ledger, &group);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].field, "to_entity");
+    }
+
+    #[test]
+    fn test_refund_is_negative() {
+        let refund = |amount: f64| {
+            transaction(&format!(
+                "kind = \"refund\"\namount = {amount:.1}\n\n[[split_ratios]]\nentity_id = \"{ANA}\"\nshares = 1"
+            ))
+        };
+        let (group, ledger) = (group(), ledger());
+        let sut = GroupValidator::new();
+
+        assert!(
+            sut.validate_transaction(&refund(-10.0), &ledger, &group)
+                .is_valid
+        );
+        let result = sut.validate_transaction(&refund(10.0), &ledger, &group);
+        assert_eq!(result.errors[0].field, "amount");
+    }
+
+    #[test]
+    fn test_signed_commits_only_policy() {
+        let revision = Revision {
+            revision_id: String::from("abc1234"),
+            author: String::from("Mallory"),
+            email: String::from("mallory@example.org"),
+            time: dates::now(),
+            message: String::from("Add dinner"),
+        };
+        let verified = SignatureStatus::Verified(Uuid::from_u128(1));
+
+        let lenient = GroupValidator::new();
+        assert!(
+            lenient
+                .validate_signature(&revision, &SignatureStatus::Unsigned)
+                .is_ok()
+        );
+
+        let strict = GroupValidator::new().signed_commits_only(true);
+        assert!(strict.validate_signature(&revision, &verified).is_ok());
+        assert!(
+            strict
+                .validate_signature(&revision, &SignatureStatus::UnknownKey)
+                .is_err()
+        );
+        assert!(
+            strict
+                .validate_signature(&revision, &SignatureStatus::Unsigned)
+                .is_err()
+        );
+        assert!(
+            strict
+                .validate_signature(&revision, &SignatureStatus::Unverifiable)
+                .is_err()
+        );
+    }
+}